@@ -0,0 +1,53 @@
+//! Throughput benchmarks for the simulation.
+//!
+//! Run with `cargo bench`. Each benchmark reports the time per iteration and
+//! the number of cells updated per second, which makes it easy to compare
+//! optimizations of the rule lookup and the step function against a saved
+//! baseline.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use eca1d::seed::single_cell_seed;
+use eca1d::Ca;
+
+const RULES: [u8; 4] = [30, 90, 110, 184];
+const WIDTHS: [usize; 3] = [64, 512, 4096];
+
+fn bench_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    for &rule in RULES.iter() {
+        for &width in WIDTHS.iter() {
+            group.throughput(Throughput::Elements(width as u64));
+            let mut ca = Ca::new(single_cell_seed(width, width / 2).unwrap(), rule);
+            group.bench_function(BenchmarkId::new(format!("rule{}", rule), width), |b| {
+                b.iter(|| {
+                    ca.step();
+                    black_box(&ca);
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_run(c: &mut Criterion) {
+    const STEPS: usize = 256;
+    let mut group = c.benchmark_group(format!("run{}", STEPS));
+    for &rule in RULES.iter() {
+        for &width in WIDTHS.iter() {
+            group.throughput(Throughput::Elements((width * STEPS) as u64));
+            group.bench_function(BenchmarkId::new(format!("rule{}", rule), width), |b| {
+                b.iter(|| {
+                    let mut ca = Ca::new(single_cell_seed(width, width / 2).unwrap(), rule);
+                    black_box(ca.run(STEPS))
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_step, bench_run);
+criterion_main!(benches);
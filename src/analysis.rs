@@ -0,0 +1,790 @@
+//! Analysis of automaton states and generations.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use crate::BoundaryCondition;
+
+/// Returns the number of ON cells in `row`.
+pub fn population(row: &[u8]) -> usize {
+    row.iter().filter(|el| **el > 0).count()
+}
+
+/// Returns the fraction of cells that changed between two generations.
+///
+/// Cells past the end of the shorter generation are ignored.
+pub fn activity(before: &[u8], after: &[u8]) -> f64 {
+    let len = before.len().min(after.len());
+    if len == 0 {
+        return 0.;
+    }
+    let changed = before
+        .iter()
+        .zip(after)
+        .filter(|(a, b)| (**a > 0) != (**b > 0))
+        .count();
+    changed as f64 / len as f64
+}
+
+/// Returns the fraction of generations each column is ON in.
+///
+/// The profile reveals spatial asymmetries, for example from the boundary
+/// condition or the position of the seed. Columns missing from shorter rows
+/// count as OFF.
+///
+/// # Arguments
+/// * `gens` - The generations, where values >0 are interpreted as ON.
+pub fn density_profile(gens: &[Vec<u8>]) -> Vec<f64> {
+    let width = gens.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut counts = vec![0usize; width];
+    for row in gens {
+        for (count, el) in counts.iter_mut().zip(row) {
+            if *el > 0 {
+                *count += 1;
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .map(|count| count as f64 / gens.len() as f64)
+        .collect()
+}
+
+/// Returns the Shannon entropy, in bits, of the cell states in `row`.
+///
+/// The entropy is 0 for rows where all cells are in the same state and 1 for
+/// rows where half of the cells are ON.
+pub fn entropy(row: &[u8]) -> f64 {
+    if row.is_empty() {
+        return 0.;
+    }
+    let p = population(row) as f64 / row.len() as f64;
+    [p, 1. - p]
+        .iter()
+        .filter(|p| **p > 0.)
+        .map(|p| -p * p.log2())
+        .sum()
+}
+
+/// Returns the number of distinct subwords of length `k` in `row`.
+///
+/// This is the subword complexity of the row, a simple measure of pattern
+/// diversity that complements [`entropy`]. The windows don't wrap around the
+/// edges, and the result is 0 if `k` is 0 or longer than the row.
+///
+/// # Arguments
+/// * `row` - A generation, where values >0 are interpreted as ON.
+/// * `k` - The length of the subwords.
+pub fn distinct_windows(row: &[u8], k: usize) -> usize {
+    if k == 0 {
+        return 0;
+    }
+    row.windows(k)
+        .map(|w| w.iter().map(|el| *el > 0).collect::<Vec<bool>>())
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Returns every distinct subword of length `k` appearing in any of `gens`,
+/// sorted.
+///
+/// Run long enough from a random seed, this approximates the language of
+/// the rule, the local patterns it can generate. Patterns missing from the
+/// result after the first few generations are likely forbidden by the rule.
+/// Like in [`distinct_windows`] the windows don't wrap around the edges.
+///
+/// # Arguments
+/// * `gens` - The generations, where values >0 are interpreted as ON.
+/// * `k` - The length of the subwords.
+pub fn observed_windows(gens: &[Vec<u8>], k: usize) -> Vec<Vec<u8>> {
+    if k == 0 {
+        return Vec::new();
+    }
+    let windows: BTreeSet<Vec<u8>> = gens
+        .iter()
+        .flat_map(|row| row.windows(k))
+        .map(|w| w.iter().map(|el| if *el > 0 { 1 } else { 0 }).collect())
+        .collect();
+    windows.into_iter().collect()
+}
+
+/// Returns the period of the first cycle in `gens`, if any generation repeats.
+///
+/// A fixed point has period 1.
+pub fn detect_period(gens: &[Vec<u8>]) -> Option<usize> {
+    let mut seen: HashMap<&[u8], usize> = HashMap::new();
+    for (i, row) in gens.iter().enumerate() {
+        if let Some(first) = seen.insert(row, i) {
+            return Some(i - first);
+        }
+    }
+    None
+}
+
+/// Counts the states that `rule` maps to `state` in one step.
+///
+/// A state without any preimages is a Garden of Eden, it can only ever appear
+/// as a seed. The count is computed by walking the de Bruijn graph of the rule
+/// (see [`crate::rule_to_dot`]) along `state`, so it takes time linear in the
+/// width rather than enumerating all `2^width` candidates.
+///
+/// The count saturates at `usize::MAX`, which wide states with many
+/// preimages reach, e.g. any state of about 64 cells or more under rule 0.
+///
+/// # Arguments
+/// * `state` - The state to find preimages of, where values >0 are
+///   interpreted as ON.
+/// * `rule` - The rule to use.
+/// * `boundary` - The boundary condition of the preimages.
+pub fn count_preimages(state: &[u8], rule: u8, boundary: BoundaryCondition) -> usize {
+    let n = state.len();
+    if n == 0 {
+        return 1;
+    }
+    let output = |a: usize, b: usize, c: usize| ((rule >> ((a << 2) | (b << 1) | c)) & 1) as usize;
+    let target = |i: usize| if state[i] > 0 { 1 } else { 0 };
+
+    // For wrapping boundaries the cells beyond the edges are cells of the
+    // preimage itself, at these indices.
+    let twist = match boundary {
+        BoundaryCondition::Wrap => Some(0),
+        BoundaryCondition::Twist(k) => Some(k % n),
+        _ => None,
+    };
+    let wrapped = twist.map(|k| ((n - 1 + n - k) % n, k));
+    // Whether `value` at `index` agrees with the guessed cells beyond the
+    // edges, `left` and `right`.
+    let consistent = |index: usize, value: usize, left: usize, right: usize| match wrapped {
+        Some((li, ri)) => (index != li || value == left) && (index != ri || value == right),
+        None => true,
+    };
+
+    let mut total = 0usize;
+    // Pick the cell left of the state and the first cell, then extend the
+    // preimage one cell at a time, counting the ways to reach each pair of
+    // last two cells. For wrapping boundaries the cell right of the state is
+    // guessed up front as well, and checked as the preimage is built.
+    let rights: &[usize] = if wrapped.is_some() { &[0, 1] } else { &[0] };
+    for left in 0..2 {
+        for first in 0..2 {
+            for &right in rights {
+                let valid_start = match boundary {
+                    BoundaryCondition::Zero => left == 0,
+                    BoundaryCondition::One => left == 1,
+                    BoundaryCondition::Reflect => left == first,
+                    BoundaryCondition::Wrap | BoundaryCondition::Twist(_) => {
+                        consistent(0, first, left, right)
+                    }
+                };
+                if !valid_start {
+                    continue;
+                }
+
+                let mut counts = [[0usize; 2]; 2];
+                counts[left][first] = 1;
+                for i in 0..n {
+                    let mut next_counts = [[0usize; 2]; 2];
+                    for (prev, row) in counts.iter().enumerate() {
+                        for (cur, &count) in row.iter().enumerate() {
+                            if count == 0 {
+                                continue;
+                            }
+                            // The cell right of the state is given by the
+                            // boundary.
+                            let candidates = if i + 1 < n {
+                                vec![0, 1]
+                            } else {
+                                match boundary {
+                                    BoundaryCondition::Zero => vec![0],
+                                    BoundaryCondition::One => vec![1],
+                                    BoundaryCondition::Reflect => vec![cur],
+                                    BoundaryCondition::Wrap | BoundaryCondition::Twist(_) => {
+                                        vec![right]
+                                    }
+                                }
+                            };
+                            for next in candidates {
+                                if i + 1 < n && !consistent(i + 1, next, left, right) {
+                                    continue;
+                                }
+                                if output(prev, cur, next) == target(i) {
+                                    next_counts[cur][next] =
+                                        next_counts[cur][next].saturating_add(count);
+                                }
+                            }
+                        }
+                    }
+                    counts = next_counts;
+                }
+                total = counts
+                    .iter()
+                    .flatten()
+                    .fold(total, |total, &count| total.saturating_add(count));
+            }
+        }
+    }
+    total
+}
+
+/// A compact profile of the behaviour of a rule from a random seed, see
+/// [`crate::Ca::summary`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleSummary {
+    /// The rule that was run.
+    pub rule: u8,
+    /// The number of ON cells in the last generation.
+    pub final_population: usize,
+    /// The mean fraction of cells changing between generations.
+    pub mean_activity: f64,
+    /// The mean entropy of the generations, see [`entropy`].
+    pub mean_entropy: f64,
+    /// The period of the cycle the automaton ended up in, if one was found.
+    pub period: Option<usize>,
+    /// Whether all cells died.
+    pub extinct: bool,
+}
+
+impl RuleSummary {
+    /// Computes the summary of `gens`, the generations produced by `rule`.
+    pub fn from_generations(rule: u8, gens: &[Vec<u8>]) -> RuleSummary {
+        let final_population = gens.last().map_or(0, |row| population(row));
+        let transitions = gens.len().saturating_sub(1).max(1) as f64;
+        let mean_activity =
+            gens.windows(2).map(|w| activity(&w[0], &w[1])).sum::<f64>() / transitions;
+        let mean_entropy = if gens.is_empty() {
+            0.
+        } else {
+            gens.iter().map(|row| entropy(row)).sum::<f64>() / gens.len() as f64
+        };
+        RuleSummary {
+            rule,
+            final_population,
+            mean_activity,
+            mean_entropy,
+            period: detect_period(gens),
+            extinct: !gens.is_empty() && final_population == 0,
+        }
+    }
+}
+
+impl fmt::Display for RuleSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "rule:             {}", self.rule)?;
+        writeln!(f, "final population: {}", self.final_population)?;
+        writeln!(f, "mean activity:    {:.3}", self.mean_activity)?;
+        writeln!(f, "mean entropy:     {:.3}", self.mean_entropy)?;
+        match self.period {
+            Some(period) => writeln!(f, "period:           {}", period)?,
+            None => writeln!(f, "period:           none found")?,
+        }
+        writeln!(
+            f,
+            "extinct:          {}",
+            if self.extinct { "yes" } else { "no" }
+        )
+    }
+}
+
+/// Returns, for each of `steps` generations, the leftmost and rightmost
+/// columns that could be ON when starting from `seed`.
+///
+/// Information travels at most one cell per step, so in generation `n` only
+/// cells within `n` columns of an ON cell in the seed can be ON. The bounds are
+/// clamped to the width of the seed and don't take wrapping into account.
+/// This only holds for rules mapping the pattern `000` to 0, for other rules
+/// the whole row may be ON from the first step.
+///
+/// Returns `None` for every generation if the seed has no ON cells.
+///
+/// # Arguments
+/// * `seed` - The starting state, where values >0 are interpreted as ON.
+/// * `steps` - The number of generations, including the seed.
+pub fn light_cone(seed: &[u8], steps: usize) -> Vec<Option<(usize, usize)>> {
+    let first = seed.iter().position(|el| *el > 0);
+    let last = seed.iter().rposition(|el| *el > 0);
+    (0..steps)
+        .map(|n| match (first, last) {
+            (Some(l), Some(r)) => Some((l.saturating_sub(n), (r + n).min(seed.len() - 1))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the normalized circular autocorrelation of `row` at every lag.
+///
+/// ON cells are treated as +1 and OFF cells as -1, so the value at each lag is
+/// between -1 (the row is the inverse of itself shifted by the lag) and 1 (the
+/// row is identical to itself shifted by the lag). The value at lag 0 is
+/// always 1 and peaks at other lags reveal the spatial periods of the row.
+///
+/// # Arguments
+/// * `row` - A generation, where values >0 are interpreted as ON.
+pub fn autocorrelation(row: &[u8]) -> Vec<f64> {
+    let n = row.len();
+    let signs: Vec<f64> = row
+        .iter()
+        .map(|el| if *el > 0 { 1. } else { -1. })
+        .collect();
+    (0..n)
+        .map(|lag| {
+            let sum: f64 = (0..n).map(|i| signs[i] * signs[(i + lag) % n]).sum();
+            sum / n as f64
+        })
+        .collect()
+}
+
+/// Returns the power spectrum of the time series `series`, for example the
+/// population of every generation.
+///
+/// The mean is subtracted before a discrete Fourier transform, so the value
+/// at frequency 0 is always 0. The value at index `k` is the power at `k /
+/// series.len()` cycles per sample, for `k` in `0..=series.len() / 2`.
+///
+/// # Arguments
+/// * `series` - The values to analyse, one per generation.
+pub fn power_spectrum(series: &[f64]) -> Vec<f64> {
+    let n = series.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let mean = series.iter().sum::<f64>() / n as f64;
+    (0..=n / 2)
+        .map(|k| {
+            let (re, im) = series
+                .iter()
+                .enumerate()
+                .fold((0., 0.), |(re, im), (t, x)| {
+                    let angle = -2. * std::f64::consts::PI * (k * t) as f64 / n as f64;
+                    (re + (x - mean) * angle.cos(), im + (x - mean) * angle.sin())
+                });
+            (re * re + im * im) / n as f64
+        })
+        .collect()
+}
+
+/// Returns the frequency, in cycles per generation, with the most power in
+/// [`power_spectrum`] of `series`.
+///
+/// An oscillation with period `p` gives a frequency of about `1 / p`. Returns
+/// `None` if `series` is constant, or too short to contain an oscillation.
+///
+/// # Arguments
+/// * `series` - The values to analyse, one per generation.
+pub fn dominant_frequency(series: &[f64]) -> Option<f64> {
+    let spectrum = power_spectrum(series);
+    spectrum
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, power)| **power > 1e-9)
+        .fold(None, |best: Option<(usize, f64)>, (k, power)| match best {
+            Some((_, max)) if max >= *power => best,
+            _ => Some((k, *power)),
+        })
+        .map(|(k, _)| k as f64 / series.len() as f64)
+}
+
+/// Estimates the dominant drift velocity, in cells per step, of the structures
+/// in a space-time diagram.
+///
+/// Every generation is cross-correlated with the previous one shifted by each
+/// amount in `-max_shift..=max_shift`, wrapping around the edges, with ON
+/// cells treated as +1 and OFF cells as -1 like in [`autocorrelation`]. The
+/// shift with the largest total correlation is returned, where a positive
+/// shift means that the structures move to the right. Ties are broken in
+/// favour of the slowest shift.
+///
+/// Returns `None` if there are fewer than two generations or if the rows are
+/// empty or don't all have the same length.
+///
+/// # Arguments
+/// * `gens` - The generations, where values >0 are interpreted as ON.
+/// * `max_shift` - The largest speed, in either direction, to consider.
+pub fn drift_velocity(gens: &[Vec<u8>], max_shift: usize) -> Option<isize> {
+    let n = gens.first().map_or(0, |row| row.len());
+    if gens.len() < 2 || n == 0 || gens.iter().any(|row| row.len() != n) {
+        return None;
+    }
+    let sign = |el: u8| if el > 0 { 1. } else { -1. };
+    let correlation = |shift: isize| -> f64 {
+        gens.windows(2)
+            .map(|pair| {
+                (0..n)
+                    .map(|i| {
+                        let from = (i as isize - shift).rem_euclid(n as isize) as usize;
+                        sign(pair[0][from]) * sign(pair[1][i])
+                    })
+                    .sum::<f64>()
+            })
+            .sum()
+    };
+
+    let mut best = (0, correlation(0));
+    for speed in 1..=max_shift as isize {
+        for shift in [speed, -speed] {
+            let c = correlation(shift);
+            if c > best.1 {
+                best = (shift, c);
+            }
+        }
+    }
+    Some(best.0)
+}
+
+/// Estimates the fractal (box-counting) dimension of a space-time diagram.
+///
+/// The diagram is covered with square boxes of sizes 1, 2, 4, ... up to a
+/// quarter of its smallest side, counting the boxes containing at least one ON
+/// cell at each size. The dimension is the negated slope of a least squares
+/// line fitted to the logarithm of the counts against the logarithm of the box
+/// sizes.
+///
+/// This is only an estimate, finite diagrams have no true fractal dimension
+/// and the result depends on their size. Returns 0 if the diagram has no ON
+/// cells or is too small to be measured at two scales.
+///
+/// # Arguments
+/// * `gens` - The generations, where values >0 are interpreted as ON.
+pub fn fractal_dimension(gens: &[Vec<u8>]) -> f64 {
+    let height = gens.len();
+    let width = gens.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    let mut points = Vec::new();
+    let mut size = 1;
+    while size == 1 || size * 4 <= height.min(width) {
+        let mut boxes = 0;
+        for top in (0..height).step_by(size) {
+            for left in (0..width).step_by(size) {
+                let occupied = gens[top..(top + size).min(height)]
+                    .iter()
+                    .any(|row| row.iter().skip(left).take(size).any(|el| *el > 0));
+                if occupied {
+                    boxes += 1;
+                }
+            }
+        }
+        if boxes == 0 {
+            return 0.;
+        }
+        points.push(((size as f64).ln(), (boxes as f64).ln()));
+        size *= 2;
+    }
+    if points.len() < 2 {
+        return 0.;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let cov: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let var: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    -cov / var
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::Ca;
+
+    fn brute_force_preimages(state: &[u8], rule: u8, boundary: BoundaryCondition) -> usize {
+        let n = state.len();
+        (0..1usize << n)
+            .filter(|bits| {
+                let candidate: Vec<u8> = (0..n).map(|i| ((bits >> i) & 1) as u8).collect();
+                crate::apply_rule(&candidate, rule, boundary) == state
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_count_preimages_matches_enumeration() {
+        let boundaries = [
+            BoundaryCondition::Wrap,
+            BoundaryCondition::Zero,
+            BoundaryCondition::One,
+            BoundaryCondition::Reflect,
+            BoundaryCondition::Twist(1),
+            BoundaryCondition::Twist(2),
+            BoundaryCondition::Twist(7),
+        ];
+        let states: [&[u8]; 5] = [
+            &[1],
+            &[0, 1],
+            &[0, 1, 0, 1, 1],
+            &[1, 1, 1, 0, 0, 0],
+            &[0; 6],
+        ];
+        for &rule in &[0, 30, 90, 110, 150, 184, 255] {
+            for &boundary in boundaries.iter() {
+                for state in states.iter() {
+                    assert_eq!(
+                        brute_force_preimages(state, rule, boundary),
+                        count_preimages(state, rule, boundary),
+                        "rule {} {:?} {:?}",
+                        rule,
+                        boundary,
+                        state
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_preimages_garden_of_eden() {
+        // Rule 0 can only produce the empty state.
+        assert_eq!(0, count_preimages(&[0, 1, 0], 0, BoundaryCondition::Wrap));
+        assert_eq!(8, count_preimages(&[0, 0, 0], 0, BoundaryCondition::Wrap));
+        // Rule 204 is the identity, so every state has exactly one preimage.
+        assert_eq!(
+            1,
+            count_preimages(&[1, 0, 1, 1], 204, BoundaryCondition::Wrap)
+        );
+    }
+
+    #[test]
+    fn test_count_preimages_saturates() {
+        assert_eq!(
+            1 << 60,
+            count_preimages(&[0; 60], 0, BoundaryCondition::Wrap)
+        );
+        assert_eq!(
+            usize::MAX,
+            count_preimages(&[0; 70], 0, BoundaryCondition::Zero)
+        );
+        assert_eq!(
+            usize::MAX,
+            count_preimages(&[0; 200], 0, BoundaryCondition::Wrap)
+        );
+    }
+
+    #[test]
+    fn test_population_activity_entropy() {
+        assert_eq!(2, population(&[0, 1, 0, 3]));
+        assert!((activity(&[0, 1, 0, 1], &[0, 0, 1, 1]) - 0.5).abs() < 1e-12);
+        assert_eq!(0., entropy(&[1, 1, 1, 1]));
+        assert!((entropy(&[1, 0, 1, 0]) - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_density_profile() {
+        let gens = vec![
+            vec![1, 0, 1, 0],
+            vec![1, 1, 0, 0],
+            vec![1, 0, 0, 0],
+            vec![1, 1, 0, 0],
+        ];
+        assert_eq!(vec![1.0, 0.5, 0.25, 0.0], density_profile(&gens));
+        assert!(density_profile(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_distinct_windows() {
+        let row = [0, 1, 0, 1, 0, 1, 0, 1];
+        assert_eq!(2, distinct_windows(&row, 2));
+        assert_eq!(2, distinct_windows(&row, 3));
+        assert_eq!(2, distinct_windows(&row, 1));
+        assert_eq!(4, distinct_windows(&[0, 0, 1, 1, 0, 0], 2));
+        assert_eq!(1, distinct_windows(&[0, 2, 1], 3));
+    }
+
+    #[test]
+    fn test_distinct_windows_degenerate() {
+        assert_eq!(0, distinct_windows(&[0, 1], 0));
+        assert_eq!(0, distinct_windows(&[0, 1], 3));
+        assert_eq!(0, distinct_windows(&[], 1));
+    }
+
+    #[test]
+    fn test_observed_windows() {
+        // Rule 0 kills everything after the seed.
+        let gens = Ca::new(vec![0, 1, 1, 0, 1], 0).run(5);
+        assert_eq!(
+            vec![vec![0, 0], vec![0, 1], vec![1, 0], vec![1, 1]],
+            observed_windows(&gens, 2)
+        );
+        assert_eq!(
+            vec![vec![0, 0, 0], vec![0, 1, 1], vec![1, 0, 1], vec![1, 1, 0]],
+            observed_windows(&gens, 3)
+        );
+        assert_eq!(vec![vec![0, 0, 0, 0, 0]], observed_windows(&gens[1..], 5));
+    }
+
+    #[test]
+    fn test_observed_windows_degenerate() {
+        assert!(observed_windows(&[vec![0, 1]], 0).is_empty());
+        assert!(observed_windows(&[vec![0, 1]], 3).is_empty());
+        assert!(observed_windows(&[], 2).is_empty());
+    }
+
+    #[test]
+    fn test_detect_period() {
+        let gens = vec![vec![0, 1], vec![1, 0], vec![0, 1], vec![1, 0]];
+        assert_eq!(Some(2), detect_period(&gens));
+        assert_eq!(None, detect_period(&gens[..2]));
+    }
+
+    #[test]
+    fn test_summary_rule_0() {
+        let summary = Ca::summary(0, 32, 10, 0.5, &mut StdRng::seed_from_u64(1));
+        assert_eq!(0, summary.rule);
+        assert_eq!(0, summary.final_population);
+        assert!(summary.extinct);
+        assert_eq!(Some(1), summary.period);
+        assert!(summary.mean_activity > 0.);
+    }
+
+    #[test]
+    fn test_summary_identity_rule() {
+        let summary = Ca::summary(204, 32, 10, 1., &mut StdRng::seed_from_u64(1));
+        assert_eq!(
+            RuleSummary {
+                rule: 204,
+                final_population: 32,
+                mean_activity: 0.,
+                mean_entropy: 0.,
+                period: Some(1),
+                extinct: false,
+            },
+            summary
+        );
+        assert!(summary.to_string().contains("period:           1"));
+    }
+
+    #[test]
+    fn test_fractal_dimension_rule_90() {
+        let mut seed = vec![0; 1025];
+        seed[512] = 1;
+        let gens = Ca::new(seed, 90).run(512);
+        let dimension = fractal_dimension(&gens);
+        // The Sierpinski triangle has dimension log(3) / log(2) ~ 1.585.
+        assert!((dimension - 1.585).abs() < 0.1, "{}", dimension);
+    }
+
+    #[test]
+    fn test_fractal_dimension_filled() {
+        let dimension = fractal_dimension(&vec![vec![1; 64]; 64]);
+        assert!((dimension - 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fractal_dimension_empty() {
+        assert_eq!(0., fractal_dimension(&vec![vec![0; 16]; 16]));
+        assert_eq!(0., fractal_dimension(&[]));
+    }
+
+    #[test]
+    fn test_autocorrelation_periodic() {
+        let corr = autocorrelation(&[1, 0, 1, 0, 1, 0, 1, 0]);
+        for (lag, c) in corr.into_iter().enumerate() {
+            let expected = if lag % 2 == 0 { 1. } else { -1. };
+            assert!((c - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_autocorrelation_period_3() {
+        let corr = autocorrelation(&[1, 0, 0, 1, 0, 0]);
+        assert!((corr[0] - 1.).abs() < 1e-12);
+        assert!((corr[3] - 1.).abs() < 1e-12);
+        assert!(corr[1] < 1.);
+        assert!(corr[2] < 1.);
+    }
+
+    #[test]
+    fn test_autocorrelation_empty() {
+        assert!(autocorrelation(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_dominant_frequency_sine() {
+        let series: Vec<f64> = (0..64)
+            .map(|t| 10. + (2. * std::f64::consts::PI * t as f64 / 8.).sin())
+            .collect();
+        assert_eq!(Some(0.125), dominant_frequency(&series));
+        let spectrum = power_spectrum(&series);
+        assert_eq!(33, spectrum.len());
+        assert!(spectrum[0].abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dominant_frequency_population() {
+        // Rule 1 blinks between all OFF and all ON from an empty row.
+        let gens = Ca::new(vec![0; 8], 1).run(16);
+        let series: Vec<f64> = gens.iter().map(|row| population(row) as f64).collect();
+        assert_eq!(Some(0.5), dominant_frequency(&series));
+    }
+
+    #[test]
+    fn test_dominant_frequency_constant() {
+        assert_eq!(None, dominant_frequency(&[3., 3., 3., 3.]));
+        assert_eq!(None, dominant_frequency(&[1.]));
+        assert_eq!(None, dominant_frequency(&[]));
+        assert!(power_spectrum(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_drift_velocity_shifts() {
+        let seed = vec![0, 1, 1, 0, 1, 0, 0, 0, 1, 0, 0, 0];
+        // Rule 16 copies the left neighbour, moving everything to the right.
+        let gens = Ca::new(seed.clone(), 16).run(6);
+        assert_eq!(Some(1), drift_velocity(&gens, 3));
+        // Rule 2 copies the right neighbour, moving everything to the left.
+        let gens = Ca::new(seed.clone(), 2).run(6);
+        assert_eq!(Some(-1), drift_velocity(&gens, 3));
+        // Rule 204 is the identity.
+        let gens = Ca::new(seed, 204).run(6);
+        assert_eq!(Some(0), drift_velocity(&gens, 3));
+    }
+
+    #[test]
+    fn test_drift_velocity_traffic() {
+        // Sparse traffic in rule 184 flows freely to the right.
+        let mut seed = vec![0; 40];
+        for i in (0..40).step_by(5) {
+            seed[i] = 1;
+        }
+        let gens = Ca::new(seed, 184).run(10);
+        assert_eq!(Some(1), drift_velocity(&gens, 4));
+    }
+
+    #[test]
+    fn test_drift_velocity_too_short() {
+        assert_eq!(None, drift_velocity(&[vec![1, 0, 1]], 1));
+        assert_eq!(None, drift_velocity(&[vec![], vec![]], 1));
+    }
+
+    #[test]
+    fn test_drift_velocity_ragged() {
+        assert_eq!(None, drift_velocity(&[vec![0, 1, 0], vec![1]], 1));
+        assert_eq!(None, drift_velocity(&[vec![0, 1], vec![0, 1, 0]], 1));
+    }
+
+    #[test]
+    fn test_light_cone_single_cell() {
+        let mut seed = vec![0; 21];
+        seed[10] = 1;
+        let cone = light_cone(&seed, 5);
+        assert_eq!(5, cone.len());
+        for (n, bounds) in cone.into_iter().enumerate() {
+            assert_eq!(Some((10 - n, 10 + n)), bounds);
+        }
+    }
+
+    #[test]
+    fn test_light_cone_clamps_to_width() {
+        let cone = light_cone(&[0, 1, 0, 1, 0], 4);
+        assert_eq!(
+            vec![Some((1, 3)), Some((0, 4)), Some((0, 4)), Some((0, 4))],
+            cone
+        );
+    }
+
+    #[test]
+    fn test_light_cone_empty_seed() {
+        assert_eq!(vec![None, None], light_cone(&[0, 0, 0], 2));
+    }
+}
@@ -0,0 +1,393 @@
+//! Exporting generations to other formats.
+
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+use crate::CaError;
+
+/// The bytes every file in the binary format starts with.
+const BINARY_MAGIC: &[u8; 4] = b"ECA1";
+/// The version of the binary format written by [`generations_to_binary`].
+const BINARY_VERSION: u8 = 1;
+/// The length of the header of the binary format.
+const BINARY_HEADER_LEN: usize = 14;
+
+/// Writes `gens` as CSV with one generation per line.
+///
+/// Each cell is written as 0 or 1 and every line, including the last, ends in
+/// a newline. Rows aren't required to have the same length, each row is
+/// written with as many values as it contains.
+///
+/// # Arguments
+/// * `gens` - The generations to write, for example from [`crate::Ca::run`].
+/// * `w` - The writer to write the CSV into.
+pub fn generations_to_csv<W: Write>(gens: &[Vec<u8>], w: &mut W) -> io::Result<()> {
+    for row in gens {
+        let cells: Vec<&str> = row
+            .iter()
+            .map(|el| if *el > 0 { "1" } else { "0" })
+            .collect();
+        writeln!(w, "{}", cells.join(","))?;
+    }
+    Ok(())
+}
+
+/// Formats `gens` as a Rust `vec!` literal, for pasting into tests.
+///
+/// For example two generations of rule 90 become
+/// `vec![vec![0, 1, 0], vec![1, 0, 1]]`.
+///
+/// # Arguments
+/// * `gens` - The generations to format, for example from [`crate::Ca::run`].
+pub fn generations_to_rust_literal(gens: &[Vec<u8>]) -> String {
+    let rows: Vec<String> = gens
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row.iter().map(|el| el.to_string()).collect();
+            format!("vec![{}]", cells.join(", "))
+        })
+        .collect();
+    format!("vec![{}]", rows.join(", "))
+}
+
+/// Writes `gens` in a compact binary format, for caching long runs on disk.
+///
+/// The layout, with integers stored little endian, is
+///
+/// | Offset | Size | Content                                      |
+/// |--------|------|----------------------------------------------|
+/// | 0      | 4    | The magic bytes `ECA1`                       |
+/// | 4      | 1    | The format version, currently 1              |
+/// | 5      | 1    | The rule                                     |
+/// | 6      | 4    | The width in cells, as a `u32`               |
+/// | 10     | 4    | The number of generations, as a `u32`        |
+/// | 14     |      | The generations                              |
+///
+/// Every generation is packed into `(width + 7) / 8` bytes, least significant
+/// bit first, with ON cells as set bits. Rows shorter than the longest one
+/// are padded with OFF cells. Read the generations back with
+/// [`generations_from_binary`].
+///
+/// # Arguments
+/// * `gens` - The generations to write, for example from [`crate::Ca::run`].
+/// * `rule` - The rule the generations were created with.
+/// * `w` - The writer to write the data into.
+///
+/// # Errors
+/// Returns an error if writing fails, if the generations have no cells but
+/// aren't empty or if the width or number of generations doesn't fit in a
+/// `u32`.
+pub fn generations_to_binary<W: Write>(gens: &[Vec<u8>], rule: u8, w: &mut W) -> io::Result<()> {
+    let width = gens.iter().map(|row| row.len()).max().unwrap_or(0);
+    let too_large = |_| io::Error::new(io::ErrorKind::InvalidInput, "too many cells for u32");
+    if width == 0 && !gens.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "generations without any cells",
+        ));
+    }
+    let width_bytes = u32::try_from(width).map_err(too_large)?.to_le_bytes();
+    let height_bytes = u32::try_from(gens.len()).map_err(too_large)?.to_le_bytes();
+
+    let mut header = Vec::with_capacity(BINARY_HEADER_LEN);
+    header.extend_from_slice(BINARY_MAGIC);
+    header.push(BINARY_VERSION);
+    header.push(rule);
+    header.extend_from_slice(&width_bytes);
+    header.extend_from_slice(&height_bytes);
+    w.write_all(&header)?;
+
+    let mut packed = vec![0u8; width.div_ceil(8)];
+    for row in gens {
+        packed.iter_mut().for_each(|byte| *byte = 0);
+        for (i, el) in row.iter().enumerate() {
+            if *el > 0 {
+                packed[i / 8] |= 1 << (i % 8);
+            }
+        }
+        w.write_all(&packed)?;
+    }
+    Ok(())
+}
+
+/// Reads generations written by [`generations_to_binary`], returning the
+/// rule and the generations.
+///
+/// # Arguments
+/// * `data` - The contents of the file.
+///
+/// # Errors
+/// Returns an error if `data` doesn't start with the magic bytes, has an
+/// unsupported version, its length doesn't match the header or it claims
+/// generations without any cells.
+pub fn generations_from_binary(data: &[u8]) -> Result<(u8, Vec<Vec<u8>>), CaError> {
+    if data.len() < BINARY_HEADER_LEN || &data[..4] != BINARY_MAGIC {
+        return Err(CaError::InvalidBinary(String::from(
+            "not binary generations, missing the ECA1 header",
+        )));
+    }
+    if data[4] != BINARY_VERSION {
+        return Err(CaError::InvalidBinary(format!(
+            "unsupported binary generations version {}",
+            data[4]
+        )));
+    }
+    let rule = data[5];
+    let width = u32::from_le_bytes([data[6], data[7], data[8], data[9]]) as usize;
+    let height = u32::from_le_bytes([data[10], data[11], data[12], data[13]]) as usize;
+
+    // Without any cells the length can't limit the number of generations.
+    if width == 0 && height != 0 {
+        return Err(CaError::InvalidBinary(format!(
+            "{} generations without any cells",
+            height
+        )));
+    }
+    let row_len = width.div_ceil(8);
+    let body = &data[BINARY_HEADER_LEN..];
+    if row_len.checked_mul(height) != Some(body.len()) {
+        return Err(CaError::InvalidBinary(format!(
+            "expected {} generations of {} cells, got {} bytes",
+            height,
+            width,
+            body.len()
+        )));
+    }
+    // A width of 0 has no bytes to split into rows.
+    if row_len == 0 {
+        return Ok((rule, Vec::new()));
+    }
+    let gens = body
+        .chunks(row_len)
+        .map(|packed| (0..width).map(|i| (packed[i / 8] >> (i % 8)) & 1).collect())
+        .collect();
+    Ok((rule, gens))
+}
+
+/// Encodes an RGBA image as a PNG file.
+///
+/// The image data is stored without compression, which keeps the encoder
+/// small and dependency-free at the cost of larger files.
+///
+/// # Arguments
+/// * `width` - The width of the image in pixels.
+/// * `height` - The height of the image in pixels.
+/// * `rgba` - The pixels row by row, 4 bytes per pixel.
+/// * `comment` - Text stored under the `Comment` keyword, if any.
+pub(crate) fn encode_png(
+    width: usize,
+    height: usize,
+    rgba: &[u8],
+    comment: Option<&str>,
+) -> Vec<u8> {
+    // Every scanline starts with a filter type byte, 0 means no filtering.
+    let mut raw = Vec::with_capacity(height * (width * 4 + 1));
+    for row in rgba.chunks(width * 4).take(height) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // A zlib stream made out of uncompressed deflate blocks.
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[0x01, 0x00, 0x00, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = if blocks.peek().is_none() { 1 } else { 0 };
+        let len = block.len() as u16;
+        zlib.push(last);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), default compression, filter and no
+    // interlacing.
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    write_png_chunk(&mut png, b"IHDR", &ihdr);
+    if let Some(comment) = comment {
+        let mut text = b"Comment\0".to_vec();
+        text.extend_from_slice(comment.as_bytes());
+        write_png_chunk(&mut png, b"tEXt", &text);
+    }
+    write_png_chunk(&mut png, b"IDAT", &zlib);
+    write_png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{seed, Ca};
+
+    /// Returns the width, height and raw pixel data of a PNG created by
+    /// `encode_png`.
+    pub(crate) fn decode_png(png: &[u8]) -> (usize, usize, Vec<u8>) {
+        let mut pos = 8;
+        let (mut width, mut height, mut zlib) = (0, 0, Vec::new());
+        while pos < png.len() {
+            let len = u32::from_be_bytes([png[pos], png[pos + 1], png[pos + 2], png[pos + 3]]);
+            let data = &png[pos + 8..pos + 8 + len as usize];
+            match &png[pos + 4..pos + 8] {
+                b"IHDR" => {
+                    width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+                    height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+                    assert_eq!(&[8, 6], &data[8..10]);
+                }
+                b"IDAT" => zlib.extend_from_slice(data),
+                _ => {}
+            }
+            pos += 12 + len as usize;
+        }
+
+        let mut raw = Vec::new();
+        let mut pos = 2;
+        loop {
+            let last = zlib[pos] & 1 == 1;
+            let len = u16::from_le_bytes([zlib[pos + 1], zlib[pos + 2]]) as usize;
+            raw.extend_from_slice(&zlib[pos + 5..pos + 5 + len]);
+            pos += 5 + len;
+            if last {
+                break;
+            }
+        }
+        let pixels = raw
+            .chunks(width * 4 + 1)
+            .flat_map(|line| line[1..].to_vec())
+            .collect();
+        (width, height, pixels)
+    }
+
+    #[test]
+    fn test_generations_to_rust_literal() {
+        assert_eq!(
+            "vec![vec![0, 1, 0], vec![1, 1, 1]]",
+            generations_to_rust_literal(&[vec![0, 1, 0], vec![1, 1, 1]])
+        );
+        assert_eq!("vec![]", generations_to_rust_literal(&[]));
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let pixels = [1, 2, 3, 4, 5, 6, 7, 8];
+        let png = encode_png(1, 2, &pixels, None);
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+        assert_eq!((1, 2, pixels.to_vec()), decode_png(&png));
+    }
+
+    #[test]
+    fn test_generations_binary_round_trip() {
+        let gens = Ca::new(seed::single_cell_seed(21, 10).unwrap(), 30).run(12);
+        let mut data = Vec::new();
+        generations_to_binary(&gens, 30, &mut data).unwrap();
+        assert_eq!(14 + 12 * 3, data.len());
+        assert_eq!(b"ECA1\x01\x1e\x15\x00\x00\x00\x0c\x00\x00\x00", &data[..14]);
+        assert_eq!(Ok((30, gens)), generations_from_binary(&data));
+    }
+
+    #[test]
+    fn test_generations_binary_edge_cases() {
+        let mut data = Vec::new();
+        generations_to_binary(&[], 90, &mut data).unwrap();
+        assert_eq!(Ok((90, vec![])), generations_from_binary(&data));
+
+        let mut data = Vec::new();
+        generations_to_binary(&[vec![1, 0, 1], vec![0, 1, 0, 1, 1]], 1, &mut data).unwrap();
+        assert_eq!(
+            Ok((1, vec![vec![1, 0, 1, 0, 0], vec![0, 1, 0, 1, 1]])),
+            generations_from_binary(&data)
+        );
+    }
+
+    #[test]
+    fn test_generations_from_binary_invalid() {
+        let mut data = Vec::new();
+        generations_to_binary(&[vec![1, 0, 1]], 1, &mut data).unwrap();
+        assert!(generations_from_binary(&data[..data.len() - 1]).is_err());
+        assert!(generations_from_binary(&data[..10]).is_err());
+        let mut version = data.clone();
+        version[4] = 2;
+        assert!(generations_from_binary(&version).is_err());
+        let mut extra = data.clone();
+        extra.push(0);
+        assert!(generations_from_binary(&extra).is_err());
+        // Empty generations would otherwise allocate as many as the header
+        // claims without any bytes backing them.
+        let mut huge = b"ECA1\x01\x1e".to_vec();
+        huge.extend_from_slice(&0u32.to_le_bytes());
+        huge.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            generations_from_binary(&huge),
+            Err(CaError::InvalidBinary(_))
+        ));
+        assert!(generations_to_binary(&[vec![], vec![]], 1, &mut Vec::new()).is_err());
+        assert!(matches!(
+            generations_from_binary(b"P4\n1 1\n\x00"),
+            Err(CaError::InvalidBinary(_))
+        ));
+    }
+
+    #[test]
+    fn test_generations_to_csv() {
+        let mut out = Vec::new();
+        generations_to_csv(&[vec![0, 1, 0], vec![1, 1, 2]], &mut out).unwrap();
+        assert_eq!(b"0,1,0\n1,1,1\n".to_vec(), out);
+    }
+
+    #[test]
+    fn test_generations_to_csv_ragged() {
+        let mut out = Vec::new();
+        generations_to_csv(&[vec![0, 1], vec![], vec![1]], &mut out).unwrap();
+        assert_eq!(b"0,1\n\n1\n".to_vec(), out);
+    }
+}
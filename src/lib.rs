@@ -1,9 +1,113 @@
+use std::borrow::Cow;
 use std::char;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::iter;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
-#[derive(Copy, Clone, Debug)]
-enum Bit {
+use rand::Rng;
+
+pub mod analysis;
+pub mod export;
+pub mod seed;
+pub mod terminal;
+
+/// The errors returned by the fallible functions of the crate.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaError {
+    /// A seed, pattern or background without any cells, naming which.
+    EmptyData(&'static str),
+    /// A seed too narrow for what was asked of it, like tiling a pattern
+    /// longer than the seed.
+    SeedTooShort { width: usize, required: usize },
+    /// A position outside of a seed.
+    OutOfBounds { index: usize, width: usize },
+    /// A character other than 0 or 1 in a pattern of cells.
+    InvalidPattern(char),
+    /// A rule that isn't a number between 0-255 or a binary string.
+    InvalidRuleString(String),
+    /// A neighbourhood size outside of 1-7.
+    InvalidNeighborhood(usize),
+    /// A rule with bits set beyond the patterns of its neighbourhood.
+    RuleTooLarge {
+        rule: u128,
+        neighborhood_size: usize,
+    },
+    /// A majority rule for an even neighbourhood without a tie break.
+    TieBreakRequired(usize),
+    /// A probability outside of `[0, 1]`.
+    InvalidProbability(f64),
+    /// Images or generations that should have the same dimensions but don't.
+    DimensionMismatch,
+    /// A malformed or unsupported image, with a description of the problem.
+    InvalidImage(String),
+    /// A file that couldn't be read, with the reason.
+    Io(String),
+    /// Malformed or unsupported binary generations, see
+    /// [`export::generations_from_binary`], with a description of the problem.
+    InvalidBinary(String),
+    /// A malformed textual specification of a `Ca`, color or boundary
+    /// condition, with a description of the problem.
+    InvalidSpec(String),
+}
+
+impl fmt::Display for CaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CaError::EmptyData(what) => write!(f, "{} can't be empty", what),
+            CaError::SeedTooShort { width, required } => write!(
+                f,
+                "width {} is too short, at least {} cells are needed",
+                width, required
+            ),
+            CaError::OutOfBounds { index, width } => {
+                write!(f, "position {} is out of bounds for width {}", index, width)
+            }
+            CaError::InvalidPattern(c) => {
+                write!(f, "invalid cell '{}' in seed, expected 0 or 1", c)
+            }
+            CaError::InvalidRuleString(s) => {
+                write!(f, "invalid rule '{}', expected a number between 0-255", s)
+            }
+            CaError::InvalidNeighborhood(size) => {
+                write!(f, "neighborhood size has to be between 1-7, got {}", size)
+            }
+            CaError::RuleTooLarge {
+                rule,
+                neighborhood_size,
+            } => write!(
+                f,
+                "rule {} is too large for a neighborhood of {} cells",
+                rule, neighborhood_size
+            ),
+            CaError::TieBreakRequired(size) => write!(
+                f,
+                "a neighborhood of {} cells can be tied, a tie break is required",
+                size
+            ),
+            CaError::InvalidProbability(p) => {
+                write!(f, "probability {} has to be between 0-1", p)
+            }
+            CaError::DimensionMismatch => write!(f, "the dimensions don't match"),
+            CaError::InvalidImage(msg)
+            | CaError::Io(msg)
+            | CaError::InvalidBinary(msg)
+            | CaError::InvalidSpec(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CaError {}
+
+/// The state of a single cell, either alive (`One`) or dead (`Zero`).
+///
+/// Converts to and from `u8`, where any value greater than 0 is `One`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Bit {
     One,
     Zero,
 }
@@ -18,15 +122,21 @@ impl From<u8> for Bit {
     }
 }
 
-impl Into<u8> for Bit {
-    fn into(self) -> u8 {
-        match self {
+impl From<Bit> for u8 {
+    fn from(bit: Bit) -> u8 {
+        match bit {
             Bit::One => 1,
             Bit::Zero => 0,
         }
     }
 }
 
+impl From<Bit> for bool {
+    fn from(bit: Bit) -> bool {
+        bit == Bit::One
+    }
+}
+
 impl fmt::Display for Bit {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -36,6 +146,20 @@ impl fmt::Display for Bit {
     }
 }
 
+/// How a cell is updated when its neighbourhood has as many live as dead
+/// cells, see [`RuleTable::majority`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TieBreak {
+    /// The cell keeps its state.
+    Stay,
+    /// The cell changes its state.
+    Flip,
+    /// The cell becomes alive.
+    Alive,
+    /// The cell dies.
+    Dead,
+}
+
 /// A table of rules for the cellular automaton.
 ///
 /// The `RuleTable` contains patterns and corresponding rules. A 0 for a given
@@ -43,7 +167,8 @@ impl fmt::Display for Bit {
 /// cell will be "alive". The patterns (for example "010") are created from the
 /// neighbouring cells in the state, where alive is interpreted as a 1 and dead
 /// is 0.
-struct RuleTable {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleTable {
     /// We use the Bit enum as the value to ensure type-safety internally.
     table: HashMap<String, Bit>,
 }
@@ -56,7 +181,7 @@ impl RuleTable {
     ///
     /// # Arguments
     /// * `rule` - The elementary 1D cellular automaton rule.
-    fn new(mut rule: u8) -> RuleTable {
+    pub fn new(mut rule: u8) -> RuleTable {
         let mut table: HashMap<String, Bit> = HashMap::new();
         for i in 0..8 {
             // We use the string representation of the pattern as a key
@@ -69,267 +194,3692 @@ impl RuleTable {
         RuleTable { table }
     }
 
-    fn get(&self, b2: Bit, b1: Bit, b0: Bit) -> Bit {
-        let key = format!("{}{}{}", b2, b1, b0);
-        *self.table.get(&key).unwrap()
-    }
-}
+    /// Creates a new `RuleTable` for neighbourhoods of `neighborhood_size`
+    /// cells from a rule number of up to 128 bits.
+    ///
+    /// Like in [`RuleTable::new`], bit `i` of the rule is the next state for
+    /// the pattern with the binary value `i`. A neighbourhood of `n` cells has
+    /// `2^n` patterns, so a `u128` covers neighbourhoods of up to 7 cells, for
+    /// example radius 3 binary automata. Only tables with a neighbourhood of 3
+    /// can be used to step a `Ca`.
+    ///
+    /// # Arguments
+    /// * `rule` - The rule number.
+    /// * `neighborhood_size` - The number of cells in each pattern, 1 to 7.
+    ///
+    /// # Errors
+    /// Returns an error if the neighbourhood size is out of range or if the
+    /// rule has bits set beyond the `2^neighborhood_size` patterns.
+    pub fn from_u128(mut rule: u128, neighborhood_size: usize) -> Result<RuleTable, CaError> {
+        if neighborhood_size == 0 || neighborhood_size > 7 {
+            return Err(CaError::InvalidNeighborhood(neighborhood_size));
+        }
+        let patterns = 1 << neighborhood_size;
+        if patterns < 128 && rule >> patterns != 0 {
+            return Err(CaError::RuleTooLarge {
+                rule,
+                neighborhood_size,
+            });
+        }
 
-/// The main simulation structure. Contains the state and the rules for a given
-/// automaton.
-pub struct Ca {
-    state: Vec<Bit>,
-    rules: RuleTable,
-}
+        let mut table: HashMap<String, Bit> = HashMap::new();
+        for i in 0..patterns {
+            table.insert(
+                format!("{:0width$b}", i, width = neighborhood_size),
+                Bit::from((rule % 2) as u8),
+            );
+            rule /= 2;
+        }
 
-impl Ca {
-    /// Returns an elementary cellular automaton ready to simulate.
+        Ok(RuleTable { table })
+    }
+
+    /// Creates a totalistic `RuleTable` where a cell becomes alive if at
+    /// least `threshold` of the `neighborhood_size` cells around it are
+    /// alive.
+    ///
+    /// If `tie` is given it decides the next state instead for the patterns
+    /// with exactly `threshold` live cells. `Stay` and `Flip` refer to the
+    /// cell at index `neighborhood_size / 2` of the pattern, the center cell
+    /// for odd neighbourhoods.
     ///
     /// # Arguments
-    /// * `seed` - A vector used as the starting point for the simulation. Any
-    ///     value greater than 0 is interpreted as occupied.
-    /// * `rule` - The rule to use. The binary value, padded with 0's, is used
-    ///     as the rule for the cellular automaton.
-    pub fn new(seed: Vec<u8>, rule: u8) -> Ca {
-        let state = seed.iter().map(|item| Bit::from(*item)).collect();
-        Ca {
-            state,
-            rules: RuleTable::new(rule),
+    /// * `neighborhood_size` - The number of cells in each pattern, 1 to 7.
+    /// * `threshold` - The number of live cells needed to become alive.
+    /// * `tie` - How to decide the patterns with exactly `threshold` live
+    ///   cells, if not simply alive.
+    ///
+    /// # Errors
+    /// Returns an error if the neighbourhood size is out of range.
+    pub fn threshold(
+        neighborhood_size: usize,
+        threshold: usize,
+        tie: Option<TieBreak>,
+    ) -> Result<RuleTable, CaError> {
+        if neighborhood_size == 0 || neighborhood_size > 7 {
+            return Err(CaError::InvalidNeighborhood(neighborhood_size));
         }
-    }
 
-    fn step(&mut self) {
-        let len = self.state.len();
-        let mut new_state = Vec::with_capacity(len);
-        new_state.push(
-            self.rules
-                .get(self.state[len - 1], self.state[0], self.state[1]),
-        );
-        for i in 1..len - 1 {
-            new_state.push(
-                self.rules
-                    .get(self.state[i - 1], self.state[i], self.state[i + 1]),
-            );
+        let mut table: HashMap<String, Bit> = HashMap::new();
+        for i in 0..1u32 << neighborhood_size {
+            let pattern = format!("{:0width$b}", i, width = neighborhood_size);
+            let alive = i.count_ones() as usize;
+            let center = pattern.as_bytes()[neighborhood_size / 2] == b'1';
+            let next = match alive.cmp(&threshold) {
+                std::cmp::Ordering::Greater => true,
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Equal => match tie {
+                    Some(TieBreak::Stay) => center,
+                    Some(TieBreak::Flip) => !center,
+                    Some(TieBreak::Alive) | None => true,
+                    Some(TieBreak::Dead) => false,
+                },
+            };
+            table.insert(pattern, Bit::from(next as u8));
         }
-        new_state.push(
-            self.rules
-                .get(self.state[len - 2], self.state[len - 1], self.state[0]),
-        );
-        self.state = new_state;
+
+        Ok(RuleTable { table })
     }
 
-    /// Runs the simulation for the specified number of steps, returning the states.
+    /// Creates a totalistic `RuleTable` where a cell becomes alive if the
+    /// majority of the `neighborhood_size` cells around it are alive.
+    ///
+    /// A neighbourhood with an even number of cells can have as many live as
+    /// dead cells, in which case `tie` decides the next state, see
+    /// [`RuleTable::threshold`]. The majority rule for 3 cells is rule 232.
     ///
     /// # Arguments
-    /// * `n` - The number of steps to run the simulation.
-    pub fn run(&mut self, n: usize) -> Vec<Vec<u8>> {
-        let mut res = Vec::with_capacity(n);
-        for _ in 0..n {
-            res.push(
-                self.state
-                    .iter()
-                    .map(|item| match item {
-                        Bit::One => 1,
-                        Bit::Zero => 0,
-                    })
-                    .collect(),
-            );
-            self.step();
+    /// * `neighborhood_size` - The number of cells in each pattern, 1 to 7.
+    /// * `tie` - How to break ties, required for even neighbourhoods.
+    ///
+    /// # Errors
+    /// Returns an error if the neighbourhood size is out of range or if it's
+    /// even and no `tie` is given.
+    pub fn majority(neighborhood_size: usize, tie: Option<TieBreak>) -> Result<RuleTable, CaError> {
+        if neighborhood_size.is_multiple_of(2) {
+            if tie.is_none() && neighborhood_size <= 7 {
+                return Err(CaError::TieBreakRequired(neighborhood_size));
+            }
+            RuleTable::threshold(neighborhood_size, neighborhood_size / 2, tie)
+        } else {
+            RuleTable::threshold(neighborhood_size, neighborhood_size / 2 + 1, None)
         }
-        res
     }
-}
 
-/// A terminal color escape sequence.
-pub enum TermColor {
-    Black,
-    Red,
-    Green,
-    Yellow,
-    Blue,
-    Magenta,
-    Cyan,
-    White,
-    Reset,
-}
+    /// Returns the rule number of the table, the inverse of
+    /// [`RuleTable::from_u128`].
+    pub fn rule_number(&self) -> u128 {
+        self.table
+            .iter()
+            .filter(|(_, &bit)| bit == Bit::One)
+            .map(|(pattern, _)| 1 << u32::from_str_radix(pattern, 2).unwrap())
+            .sum()
+    }
 
-impl TermColor {
-    fn to_fg(&self) -> String {
-        match self {
-            TermColor::Black => String::from("\x1b[30m"),
-            TermColor::Red => String::from("\x1b[31m"),
-            TermColor::Green => String::from("\x1b[32m"),
-            TermColor::Yellow => String::from("\x1b[33m"),
-            TermColor::Blue => String::from("\x1b[34m"),
-            TermColor::Magenta => String::from("\x1b[35m"),
-            TermColor::Cyan => String::from("\x1b[36m"),
-            TermColor::White => String::from("\x1b[37m"),
-            TermColor::Reset => String::from("\x1b[0m"),
+    /// Returns the number of cells in each pattern of the table.
+    pub fn neighborhood_size(&self) -> usize {
+        self.table.keys().next().map_or(0, |k| k.len())
+    }
+
+    /// Renders the table as two rows of text, with one column per pattern.
+    ///
+    /// See [`rule_table_string`] for an example. The columns are widened to
+    /// fit the patterns for neighbourhoods larger than 3 cells.
+    pub fn to_table_string(&self) -> String {
+        let size = self.neighborhood_size();
+        let (mut top, mut bottom) = (String::from("|"), String::from("|"));
+        for i in 0..self.table.len() {
+            let pattern = format!("{:0width$b}", i, width = size);
+            top.push_str(&format!(" {} |", pattern));
+            bottom.push_str(&format!(
+                "{:>left$}{:right$}|",
+                u8::from(self.table[&pattern]),
+                "",
+                left = size.div_ceil(2) + 1,
+                right = size / 2 + 1
+            ));
         }
+        format!("{}\n{}\n", top, bottom)
     }
 
-    fn to_bg(&self) -> String {
-        match self {
-            TermColor::Black => String::from("\x1b[40m"),
-            TermColor::Red => String::from("\x1b[41m"),
-            TermColor::Green => String::from("\x1b[42m"),
-            TermColor::Yellow => String::from("\x1b[43m"),
-            TermColor::Blue => String::from("\x1b[44m"),
-            TermColor::Magenta => String::from("\x1b[45m"),
-            TermColor::Cyan => String::from("\x1b[46m"),
-            TermColor::White => String::from("\x1b[47m"),
-            TermColor::Reset => String::from("\x1b[0m"),
+    /// Returns the tables of all 256 rules, indexed by rule number.
+    ///
+    /// The tables are built the first time this is called and then shared,
+    /// which avoids rebuilding them in code visiting many rules repeatedly.
+    pub fn all() -> &'static [RuleTable; 256] {
+        static ALL: OnceLock<[RuleTable; 256]> = OnceLock::new();
+        ALL.get_or_init(|| std::array::from_fn(|rule| RuleTable::new(rule as u8)))
+    }
+
+    fn get(&self, b2: Bit, b1: Bit, b0: Bit) -> Bit {
+        let key = format!("{}{}{}", b2, b1, b0);
+        *self.table.get(&key).unwrap()
+    }
+
+    /// Returns the state following `state` under this rule.
+    ///
+    /// This is the step of [`Ca::step`] without the `Ca`, for code that
+    /// keeps its own state.
+    ///
+    /// # Arguments
+    /// * `state` - The current state.
+    /// * `boundary` - How to treat the cells beyond the edges of the state.
+    ///
+    /// # Panics
+    /// Panics if the table doesn't have a neighbourhood of 3 cells.
+    pub fn apply(&self, state: &[Bit], boundary: BoundaryCondition) -> Vec<Bit> {
+        let len = state.len() as isize;
+        (0..len)
+            .map(|i| {
+                self.get(
+                    boundary.cell(state, i - 1),
+                    boundary.cell(state, i),
+                    boundary.cell(state, i + 1),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the rule table for `rule` as an array, where element `i` is the
+/// next state for the pattern with the binary value `i`.
+///
+/// Unlike [`RuleTable::new`] this is a `const fn`, so the table can be built
+/// at compile time and stored in a `static` or `const`, for example
+///
+/// ```
+/// use eca1d::{rule_array, Bit, RuleTable};
+///
+/// const RULE_90: [Bit; 8] = rule_array(90);
+/// assert_eq!(RuleTable::new(90), RuleTable::from(RULE_90));
+/// ```
+///
+/// # Arguments
+/// * `rule` - The elementary 1D cellular automaton rule.
+pub const fn rule_array(rule: u8) -> [Bit; 8] {
+    let mut table = [Bit::Zero; 8];
+    let mut i = 0;
+    while i < 8 {
+        if (rule >> i) & 1 == 1 {
+            table[i] = Bit::One;
         }
+        i += 1;
     }
+    table
 }
 
-impl fmt::Display for TermColor {
+impl From<[Bit; 8]> for RuleTable {
+    /// Creates a `RuleTable` from an array like the one from [`rule_array`].
+    fn from(array: [Bit; 8]) -> RuleTable {
+        let table = array
+            .iter()
+            .enumerate()
+            .map(|(i, bit)| (format!("{:03b}", i), *bit))
+            .collect();
+        RuleTable { table }
+    }
+}
+
+impl fmt::Display for RuleTable {
+    /// Formats the table like [`RuleTable::to_table_string`].
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_fg())
+        write!(f, "{}", self.to_table_string())
     }
 }
 
-/// A terminal 1-bit character image.
-pub struct TermImage {
-    data: Vec<Vec<u8>>,
+/// Performs a single step of `rule` on `state` without constructing a `Ca`.
+///
+/// # Arguments
+/// * `state` - The current state. Any value greater than 0 is interpreted as
+///   occupied.
+/// * `rule` - The rule to use.
+/// * `boundary` - How to treat the cells beyond the edges of the state.
+pub fn apply_rule(state: &[u8], rule: u8, boundary: BoundaryCondition) -> Vec<u8> {
+    let bits: Vec<Bit> = state.iter().map(|item| Bit::from(*item)).collect();
+    RuleTable::new(rule)
+        .apply(&bits, boundary)
+        .into_iter()
+        .map(u8::from)
+        .collect()
 }
 
-impl TermImage {
-    /// Creates a new `TermImage` from the given `data`.
-    ///
-    /// # Arguments
-    /// * `data` - The 1-bit image where values >1 are interpreted as ON and 0 is
-    ///     OFF.
-    pub fn new(data: Vec<Vec<u8>>) -> TermImage {
-        TermImage { data }
+/// Renders the rule table for `rule` as two rows of text.
+///
+/// The top row contains each of the 8 neighbourhood patterns and the bottom
+/// row the state of the cell in the next step for that pattern, for example
+///
+/// ```text
+/// | 000 | 001 | 010 | 011 | 100 | 101 | 110 | 111 |
+/// |  0  |  1  |  0  |  1  |  1  |  0  |  1  |  0  |
+/// ```
+///
+/// # Arguments
+/// * `rule` - The elementary 1D cellular automaton rule.
+pub fn rule_table_string(rule: u8) -> String {
+    RuleTable::new(rule).to_table_string()
+}
+
+/// Renders the de Bruijn graph of `rule` in the Graphviz DOT language.
+///
+/// The nodes are the 4 possible pairs of neighbouring cells and each of the 8
+/// patterns `abc` is an edge from `ab` to `bc`, labeled with the pattern and
+/// the state it gives the center cell in the next step. Paths through the
+/// graph correspond to rows of cells, so it shows which patterns a rule can
+/// produce. Pipe the output to `dot -Tpng` to draw it.
+///
+/// # Arguments
+/// * `rule` - The elementary 1D cellular automaton rule.
+pub fn rule_to_dot(rule: u8) -> String {
+    let mut res = format!("digraph rule{} {{\n", rule);
+    for node in 0..4 {
+        res.push_str(&format!("    \"{:02b}\";\n", node));
     }
+    for pattern in 0..8 {
+        res.push_str(&format!(
+            "    \"{:02b}\" -> \"{:02b}\" [label=\"{:03b}/{}\"];\n",
+            pattern >> 1,
+            pattern & 0b11,
+            pattern,
+            (rule >> pattern) & 1
+        ));
+    }
+    res.push_str("}\n");
+    res
+}
 
-    /// Render the 1-bit image using unicode HALF BLOCKS into a `String`.
-    /// 
-    /// # Arguments
-    /// * `fg` - The foreground color to use.
-    /// * `bg` - The background color to use. 
-    pub fn draw_unicode(&self, fg: TermColor, bg: TermColor) -> String {
-        let mut res = String::new();
-        for i in (0..self.data.len() - 1).step_by(2) {
-            for (top, bottom) in self.data[i].iter().zip(self.data[i + 1].iter()) {
-                let top_color = if *top > 0 { fg.to_bg() } else { bg.to_bg() };
-                let bottom_color = if *bottom > 0 { fg.to_fg() } else { bg.to_fg() };
+/// Returns the rule obtained by mirroring `rule` left to right.
+///
+/// The mirrored rule maps the pattern `abc` to the output `rule` gives the
+/// pattern `cba`, so its evolution is the mirror image of the original.
+pub fn mirror_rule(rule: u8) -> u8 {
+    (0..8).fold(0, |acc, pattern: u8| {
+        let mirrored = ((pattern & 1) << 2) | (pattern & 2) | (pattern >> 2);
+        acc | (((rule >> mirrored) & 1) << pattern)
+    })
+}
 
-                res.push_str(&format!(
-                    "{}{}▄{}",
-                    top_color,
-                    bottom_color,
-                    TermColor::Reset
-                ));
+/// Returns the rule obtained by swapping the roles of alive and dead cells.
+///
+/// The complemented rule maps a pattern to the inverse of what `rule` gives
+/// the inverted pattern, so its evolution is the inverse of the original.
+pub fn complement_rule(rule: u8) -> u8 {
+    (0..8).fold(0, |acc, pattern: u8| {
+        let output = !(rule >> (7 - pattern)) & 1;
+        acc | (output << pattern)
+    })
+}
+
+/// Returns the conventional name of `rule`, its Wolfram code, for example
+/// `"Rule 110"`.
+///
+/// # Arguments
+/// * `rule` - The elementary 1D cellular automaton rule.
+pub fn rule_name(rule: u8) -> String {
+    format!("Rule {}", rule)
+}
+
+/// Parses a rule name like the ones from [`rule_name`].
+///
+/// The `Rule` prefix is case-insensitive and the space after it is optional,
+/// so `"Rule 110"`, `"rule 110"` and `"rule110"` are all accepted.
+///
+/// # Arguments
+/// * `s` - The name to parse.
+///
+/// # Errors
+/// Returns an error if `s` isn't `Rule` followed by a number between 0-255.
+pub fn parse_rule_name(s: &str) -> Result<u8, CaError> {
+    let invalid = || CaError::InvalidRuleString(String::from(s));
+    let s = s.trim();
+    match s.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("rule") => {
+            let number = s[4..].trim_start();
+            if number.starts_with('+') {
+                return Err(invalid());
             }
-            res.push('\n');
+            number.parse().map_err(|_| invalid())
         }
-        res
+        _ => Err(invalid()),
     }
+}
 
-    // Render the 1-bit image using unicode braille symbols into a `String`.
-    /// 
-    /// # Arguments
-    /// * `fg` - The foreground color to use.
-    /// * `bg` - The background color to use. 
-    pub fn draw_braille(&self, fg: TermColor, bg: TermColor) -> String {
-        let mut res = format!("{}{}", fg.to_fg(), bg.to_bg());
-        // Iterate over 4x2 blocks of data for each braille symbol
-        for i in (0..self.data.len() - 3).step_by(4) {
-            for j in (0..self.data[i].len() - 1).step_by(2) {
-                // Each dot has its own hex-value that when added yields the
-                // symbol with it included. See wikipedia for helpful images,
-                // https://en.wikipedia.org/wiki/Braille_Patterns.
-                let dot1 = if self.data[i][j] > 0 { 0x01 } else { 0 };
-                let dot4 = if self.data[i][j + 1] > 0 { 0x08 } else { 0 };
-                let dot2 = if self.data[i + 1][j] > 0 { 0x02 } else { 0 };
-                let dot5 = if self.data[i + 1][j + 1] > 0 { 0x10 } else { 0 };
-                let dot3 = if self.data[i + 2][j] > 0 { 0x04 } else { 0 };
-                let dot6 = if self.data[i + 2][j + 1] > 0 { 0x20 } else { 0 };
-                let dot7 = if self.data[i + 3][j] > 0 { 0x40 } else { 0 };
-                let dot8 = if self.data[i + 3][j + 1] > 0 { 0x80 } else { 0 };
-
-                let codepoint = 0x2800 + dot1 + dot2 + dot3 + dot4 + dot5 + dot6 + dot7 + dot8;
-
-                res.push(char::from_u32(codepoint).expect("Invalid braille codepoint"));
-            }
-            res.push('\n');
-        }
-        res.push_str(&format!("{}", TermColor::Reset));
-        res
+/// Returns the smallest rule equivalent to `rule` under mirroring and
+/// complementing.
+///
+/// Every rule belongs to one of 88 classes of equivalent rules, and all rules
+/// in a class share the same representative.
+pub fn canonical_rule(rule: u8) -> u8 {
+    let mirrored = mirror_rule(rule);
+    *[
+        rule,
+        mirrored,
+        complement_rule(rule),
+        complement_rule(mirrored),
+    ]
+    .iter()
+    .min()
+    .unwrap()
+}
+
+/// Returns all rules for which `state` is a fixed point, i.e. stays unchanged
+/// after one step with wrapping boundaries.
+///
+/// # Arguments
+/// * `state` - The configuration to check. Any value greater than 0 is
+///   interpreted as occupied.
+pub fn rules_fixing(state: &[u8]) -> Vec<u8> {
+    let bits: Vec<Bit> = state.iter().map(|item| Bit::from(*item)).collect();
+    (0..=255)
+        .filter(|&rule| {
+            let mut ca = Ca::new(state.to_vec(), rule);
+            ca.step();
+            ca.state
+                .iter()
+                .zip(bits.iter())
+                .all(|(a, b)| u8::from(*a) == u8::from(*b))
+        })
+        .collect()
+}
+
+/// How to combine two cells in [`combine_generations`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CombineOp {
+    /// ON if either cell is ON.
+    Or,
+    /// ON if both cells are ON.
+    And,
+    /// ON if exactly one of the cells is ON.
+    Xor,
+    /// 0 if both cells are OFF, 1 if only the first is ON, 2 if only the
+    /// second is ON and 3 if both are ON.
+    Diff,
+}
+
+/// Combines two sets of generations, for example from running two rules on
+/// the same seed, cell by cell.
+///
+/// # Arguments
+/// * `a` - The first set of generations.
+/// * `b` - The second set of generations.
+/// * `op` - How to combine each pair of cells.
+///
+/// # Errors
+/// Returns an error if `a` and `b` don't have the same dimensions.
+pub fn combine_generations(
+    a: &[Vec<u8>],
+    b: &[Vec<u8>],
+    op: CombineOp,
+) -> Result<Vec<Vec<u8>>, CaError> {
+    if a.len() != b.len() || a.iter().zip(b).any(|(ra, rb)| ra.len() != rb.len()) {
+        return Err(CaError::DimensionMismatch);
     }
+    Ok(a.iter()
+        .zip(b)
+        .map(|(ra, rb)| {
+            ra.iter()
+                .zip(rb)
+                .map(|(&x, &y)| {
+                    let (x, y) = (u8::from(x > 0), u8::from(y > 0));
+                    match op {
+                        CombineOp::Or => x | y,
+                        CombineOp::And => x & y,
+                        CombineOp::Xor => x ^ y,
+                        CombineOp::Diff => x | (y << 1),
+                    }
+                })
+                .collect()
+        })
+        .collect())
+}
 
-    /// Render the 1-bit image using only ASCII symbols into a `String`.
-    pub fn draw_ascii(&self) -> String {
-        let mut res = String::new();
-        for row in self.data.iter() {
-            for el in row {
-                if *el > 0 {
-                    res.push('#');
+/// Maps the values greater than or equal to `threshold` to 1 and the rest to
+/// 0.
+fn apply_threshold(mut data: Vec<u8>, threshold: u8) -> Vec<u8> {
+    for el in data.iter_mut() {
+        *el = if *el >= threshold { 1 } else { 0 };
+    }
+    data
+}
+
+/// How the cells beyond the edges of the state are treated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BoundaryCondition {
+    /// The edges are connected so the state forms a ring.
+    Wrap,
+    /// Cells beyond the edges are always dead.
+    Zero,
+    /// Cells beyond the edges are always alive.
+    One,
+    /// Cells beyond the edges mirror the cell at the edge.
+    Reflect,
+    /// The edges are connected like `Wrap`, but shifted by a number of
+    /// cells, forming a twisted torus. The cell right of the last cell is the
+    /// cell at the offset instead of the first cell, and likewise to the left.
+    ///
+    /// A [`Ca`] grows the offset by the given amount every generation, using
+    /// `offset * generation` for the step from each generation, so structures
+    /// crossing the edge drift further every time, producing spirals. A
+    /// single step like [`apply_rule`] uses the given offset as is.
+    /// `Twist(0)` is the same as `Wrap`.
+    Twist(usize),
+}
+
+impl BoundaryCondition {
+    /// Returns the cell at `index` in `state`, resolving indices outside of
+    /// the state using the boundary condition.
+    fn cell(self, state: &[Bit], index: isize) -> Bit {
+        let len = state.len() as isize;
+        if index >= 0 && index < len {
+            return state[index as usize];
+        }
+        match self {
+            BoundaryCondition::Wrap => state[index.rem_euclid(len) as usize],
+            BoundaryCondition::Twist(offset) => {
+                let offset = (offset % state.len()) as isize;
+                let shifted = if index < 0 {
+                    index - offset
+                } else {
+                    index + offset
+                };
+                state[shifted.rem_euclid(len) as usize]
+            }
+            BoundaryCondition::Zero => Bit::Zero,
+            BoundaryCondition::One => Bit::One,
+            BoundaryCondition::Reflect => {
+                if index < 0 {
+                    state[0]
                 } else {
-                    res.push('.');
+                    state[len as usize - 1]
                 }
             }
-            res.push('\n');
         }
-        res
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::BTreeMap;
+impl fmt::Display for BoundaryCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoundaryCondition::Wrap => write!(f, "wrap"),
+            BoundaryCondition::Zero => write!(f, "zero"),
+            BoundaryCondition::One => write!(f, "one"),
+            BoundaryCondition::Reflect => write!(f, "reflect"),
+            BoundaryCondition::Twist(offset) => write!(f, "twist:{}", offset),
+        }
+    }
+}
 
-    use super::*;
+impl FromStr for BoundaryCondition {
+    type Err = CaError;
 
-    #[test]
-    fn test_rule_table_keys() {
-        let r = RuleTable::new(0);
-        let mut keys: Vec<String> = r.table.keys().map(|k| format!("{}", k)).collect();
-        keys.sort();
-        assert_eq!(
-            vec![
-                String::from("000"),
-                String::from("001"),
-                String::from("010"),
-                String::from("011"),
-                String::from("100"),
-                String::from("101"),
-                String::from("110"),
-                String::from("111")
-            ],
-            keys
-        );
+    /// Parses the lowercase name of a boundary condition, for example
+    /// `"wrap"`, or `"twist:N"` for a `Twist` with an offset of `N`.
+    fn from_str(s: &str) -> Result<BoundaryCondition, CaError> {
+        if let Some(offset) = s.strip_prefix("twist:") {
+            return offset
+                .parse()
+                .map(BoundaryCondition::Twist)
+                .map_err(|_| CaError::InvalidSpec(format!("invalid twist offset '{}'", offset)));
+        }
+        match s {
+            "wrap" => Ok(BoundaryCondition::Wrap),
+            "zero" => Ok(BoundaryCondition::Zero),
+            "one" => Ok(BoundaryCondition::One),
+            "reflect" => Ok(BoundaryCondition::Reflect),
+            _ => Err(CaError::InvalidSpec(format!(
+                "unknown boundary condition '{}'",
+                s
+            ))),
+        }
     }
+}
 
-    #[test]
-    fn test_rule_table_to_binary_rule_90() {
-        let r = RuleTable::new(90);
-        // Use the BTreeMap to order the elements by keys.
-        let table: BTreeMap<String, Bit> = r.table.iter().map(|(k, v)| (k.clone(), *v)).collect();
+/// The main simulation structure. Contains the state and the rules for a given
+/// automaton.
+///
+/// Two automata are equal if they have the same state, rule and boundary, so
+/// they evolve identically. `Ca` implements `Hash` as well, which allows
+/// storing visited configurations in a `HashSet`.
+#[derive(Eq)]
+pub struct Ca {
+    state: Vec<Bit>,
+    rules: RuleTable,
+    boundary: BoundaryCondition,
+    /// One period of the background surrounding the state, if any. Takes
+    /// precedence over `boundary`.
+    background: Option<Vec<Bit>>,
+    /// The state and background to return to on `reset`.
+    seed: Vec<Bit>,
+    seed_background: Option<Vec<Bit>>,
+    /// The number of steps taken since the seed, which the offset of a
+    /// `Twist` boundary grows with.
+    generation: usize,
+}
+
+impl Ca {
+    /// Returns an elementary cellular automaton ready to simulate.
+    ///
+    /// # Arguments
+    /// * `seed` - A vector used as the starting point for the simulation. Any
+    ///     value greater than 0 is interpreted as occupied.
+    /// * `rule` - The rule to use. The binary value, padded with 0's, is used
+    ///     as the rule for the cellular automaton.
+    #[allow(clippy::doc_overindented_list_items)]
+    pub fn new(seed: Vec<u8>, rule: u8) -> Ca {
+        Ca::with_boundary(seed, rule, BoundaryCondition::Wrap)
+    }
+
+    /// Returns an elementary cellular automaton with `seed` placed in the
+    /// middle of a ring of `circumference` cells.
+    ///
+    /// The rest of the ring is filled with OFF cells, which decouples the size
+    /// of the seed from the size of the world it evolves in.
+    ///
+    /// # Arguments
+    /// * `seed` - The cells to place on the ring. Any value greater than 0 is
+    ///   interpreted as occupied.
+    /// * `rule` - The rule to use.
+    /// * `circumference` - The number of cells in the ring.
+    ///
+    /// # Errors
+    /// Returns an error if the seed doesn't fit on the ring.
+    pub fn new_on_ring(seed: Vec<u8>, rule: u8, circumference: usize) -> Result<Ca, CaError> {
+        if seed.len() > circumference {
+            return Err(CaError::SeedTooShort {
+                width: circumference,
+                required: seed.len(),
+            });
+        }
+        let left = (circumference - seed.len()) / 2;
+        let mut state = vec![0; circumference];
+        state[left..left + seed.len()].copy_from_slice(&seed);
+        Ok(Ca::new(state, rule))
+    }
+
+    /// Returns an elementary cellular automaton starting from `state`.
+    ///
+    /// Like [`Ca::new`], but takes the cells as [`Bit`]s directly.
+    ///
+    /// # Arguments
+    /// * `state` - The starting point for the simulation.
+    /// * `rule` - The rule to use.
+    pub fn from_bits(state: Vec<Bit>, rule: u8) -> Ca {
+        Ca {
+            seed: state.clone(),
+            state,
+            rules: RuleTable::new(rule),
+            boundary: BoundaryCondition::Wrap,
+            background: None,
+            seed_background: None,
+            generation: 0,
+        }
+    }
+
+    /// Returns an elementary cellular automaton where the seed values greater
+    /// than or equal to `threshold` are interpreted as occupied.
+    ///
+    /// `Ca::new` corresponds to a `threshold` of 1.
+    ///
+    /// # Arguments
+    /// * `seed` - A vector used as the starting point for the simulation.
+    /// * `rule` - The rule to use.
+    /// * `threshold` - The smallest value interpreted as occupied.
+    pub fn with_threshold(seed: Vec<u8>, rule: u8, threshold: u8) -> Ca {
+        Ca::new(apply_threshold(seed, threshold), rule)
+    }
+
+    /// Returns an elementary cellular automaton using the given boundary
+    /// condition at the edges of the state.
+    ///
+    /// # Arguments
+    /// * `seed` - A vector used as the starting point for the simulation. Any
+    ///   value greater than 0 is interpreted as occupied.
+    /// * `rule` - The rule to use.
+    /// * `boundary` - How to treat the cells beyond the edges of the state.
+    pub fn with_boundary(seed: Vec<u8>, rule: u8, boundary: BoundaryCondition) -> Ca {
+        let state: Vec<Bit> = seed.iter().map(|item| Bit::from(*item)).collect();
+        Ca {
+            seed: state.clone(),
+            state,
+            rules: RuleTable::new(rule),
+            boundary,
+            background: None,
+            seed_background: None,
+            generation: 0,
+        }
+    }
+
+    /// Returns an elementary cellular automaton surrounded by an infinite
+    /// periodic background instead of a boundary condition.
+    ///
+    /// The background is `background` repeated in both directions, aligned so
+    /// that the first cell of the seed lines up with the first cell of a
+    /// repetition. Cells beyond the edges of the state are read from the
+    /// background, which evolves under the rule as well. This allows studying
+    /// particles travelling on a nonzero background, like the ether of rule
+    /// 110, without them being disturbed by the edges.
+    ///
+    /// # Arguments
+    /// * `seed` - A vector used as the starting point for the simulation. Any
+    ///   value greater than 0 is interpreted as occupied.
+    /// * `rule` - The rule to use.
+    /// * `background` - One period of the background.
+    ///
+    /// # Errors
+    /// Returns an error if `background` is empty.
+    pub fn with_background(seed: Vec<u8>, rule: u8, background: &[u8]) -> Result<Ca, CaError> {
+        if background.is_empty() {
+            return Err(CaError::EmptyData("background"));
+        }
+        let mut ca = Ca::new(seed, rule);
+        ca.background = Some(background.iter().map(|item| Bit::from(*item)).collect());
+        ca.seed_background = ca.background.clone();
+        Ok(ca)
+    }
+
+    /// Advances the simulation by a single step, updating the state in place.
+    ///
+    /// This is the primitive that [`Ca::run`] is built on, use it together
+    /// with [`Ca::state`] to drive the simulation one generation at a time.
+    pub fn step(&mut self) {
+        self.state = self.next_state();
+    }
+
+    /// Advances the simulation by a single asynchronous step, where only a
+    /// random fraction of the cells are updated.
+    ///
+    /// Each cell is updated by the rule with probability `p` and keeps its
+    /// state otherwise, all updated cells see the same previous state. A `p`
+    /// of 1 is the same as [`Ca::step`] and a `p` of 0 leaves the state
+    /// unchanged. A background, see [`Ca::with_background`], is always
+    /// updated in full.
+    ///
+    /// # Arguments
+    /// * `p` - The probability, between 0 and 1, of updating each cell.
+    /// * `rng` - The random number generator deciding which cells to update.
+    pub fn step_async<R: Rng>(&mut self, p: f64, rng: &mut R) {
+        let next = self.next_state();
+        for (cell, next) in self.state.iter_mut().zip(next) {
+            if rng.gen::<f64>() < p {
+                *cell = next;
+            }
+        }
+    }
+
+    /// Returns the state following the current one, advancing the background
+    /// if there is one.
+    fn next_state(&mut self) -> Vec<Bit> {
+        let boundary = self.current_boundary();
+        self.generation += 1;
+        match self.background.take() {
+            Some(background) => {
+                // Surround the state with its neighbouring background cells,
+                // which leaves only the padding to be affected by the
+                // boundary condition.
+                let period = background.len();
+                let len = self.state.len();
+                let mut padded = Vec::with_capacity(len + 2);
+                padded.push(background[period - 1]);
+                padded.extend_from_slice(&self.state);
+                padded.push(background[len % period]);
+                let next = self.rules.apply(&padded, BoundaryCondition::Zero);
+                self.background = Some(self.rules.apply(&background, BoundaryCondition::Wrap));
+                next[1..=len].to_vec()
+            }
+            None => self.rules.apply(&self.state, boundary),
+        }
+    }
+
+    /// Returns the boundary condition for the step from the current
+    /// generation, with the offset of a `Twist` grown to match it.
+    fn current_boundary(&self) -> BoundaryCondition {
+        match self.boundary {
+            BoundaryCondition::Twist(offset) if !self.state.is_empty() => {
+                let len = self.state.len() as u128;
+                let shift = offset as u128 % len * (self.generation as u128 % len) % len;
+                BoundaryCondition::Twist(shift as usize)
+            }
+            boundary => boundary,
+        }
+    }
+
+    /// Replaces the state with `seed`, keeping the rule and boundary.
+    ///
+    /// The automaton starts over from `seed` like a newly created one, so a
+    /// background, see [`Ca::with_background`], is restored to its initial
+    /// phase as well. `seed` also replaces the seed restored by
+    /// [`Ca::reset`].
+    ///
+    /// # Arguments
+    /// * `seed` - The new state. Any value greater than 0 is interpreted as
+    ///   occupied.
+    ///
+    /// # Errors
+    /// Returns an error if `seed` doesn't have the same width as the current
+    /// state.
+    pub fn reseed(&mut self, seed: Vec<u8>) -> Result<(), CaError> {
+        if seed.len() != self.state.len() {
+            return Err(CaError::DimensionMismatch);
+        }
+        self.state = seed.iter().map(|item| Bit::from(*item)).collect();
+        self.seed = self.state.clone();
+        self.background = self.seed_background.clone();
+        self.generation = 0;
+        Ok(())
+    }
+
+    /// Restores the seed the automaton was created with, or last reseeded
+    /// with, undoing every step since.
+    ///
+    /// A background, see [`Ca::with_background`], is restored as well.
+    pub fn reset(&mut self) {
+        self.state = self.seed.clone();
+        self.background = self.seed_background.clone();
+        self.generation = 0;
+    }
+
+    /// Mirrors the current state left to right, in place.
+    ///
+    /// For rules that are their own mirror image (see [`mirror_rule`]) the
+    /// evolution of the mirrored state is the mirror image of the original
+    /// evolution.
+    pub fn mirror(&mut self) {
+        self.state.reverse();
+    }
+
+    /// Returns the number of cells in the state.
+    pub fn width(&self) -> usize {
+        self.state.len()
+    }
+
+    /// Returns the rule the automaton was created with.
+    pub fn rule(&self) -> u8 {
+        // A `Ca` is always elementary, so the rule number fits in a u8.
+        self.rules.rule_number() as u8
+    }
+
+    /// Returns the current state, where 1 is an occupied cell and 0 is empty.
+    pub fn state(&self) -> Vec<u8> {
+        self.state.iter().map(|item| u8::from(*item)).collect()
+    }
+
+    /// Returns the current state as [`Bit`]s, without copying it.
+    pub fn state_bits(&self) -> &[Bit] {
+        &self.state
+    }
+
+    /// Returns the left, center and right cells the rule looks up to compute
+    /// the next state of the cell at `index`.
+    ///
+    /// The neighbours of the cells at the edges are resolved using the
+    /// background, if any, or the boundary condition, exactly like in
+    /// [`Ca::step`].
+    ///
+    /// # Arguments
+    /// * `index` - The position of the cell.
+    ///
+    /// # Errors
+    /// Returns an error if `index` is outside of the state.
+    pub fn neighborhood_at(&self, index: usize) -> Result<[u8; 3], CaError> {
+        let len = self.state.len();
+        if index >= len {
+            return Err(CaError::OutOfBounds { index, width: len });
+        }
+        let cell = |i: isize| match &self.background {
+            Some(background) if i < 0 => background[background.len() - 1],
+            Some(background) if i >= len as isize => background[len % background.len()],
+            _ => self.current_boundary().cell(&self.state, i),
+        };
+        let index = index as isize;
+        Ok([
+            u8::from(cell(index - 1)),
+            u8::from(cell(index)),
+            u8::from(cell(index + 1)),
+        ])
+    }
+
+    /// Returns an iterator lazily yielding the current state and then every
+    /// following generation.
+    ///
+    /// The iterator never ends, so bound it with for example `take`. Each
+    /// call to `next` advances the automaton by one step.
+    pub fn iter(&mut self) -> Generations<'_> {
+        Generations { ca: self }
+    }
+
+    /// Returns an iterator like [`Ca::iter`] that also yields the index of
+    /// each generation, starting at 0 for the current state.
+    pub fn indexed_iter(&mut self) -> iter::Enumerate<Generations<'_>> {
+        self.iter().enumerate()
+    }
+
+    /// Runs the simulation for the specified number of steps, returning the states.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    pub fn run(&mut self, n: usize) -> Vec<Vec<u8>> {
+        self.run_with_progress(n, |_| {})
+    }
+
+    /// Runs the simulation for the specified number of steps, returning the
+    /// states with the cells converted to `T`.
+    ///
+    /// This is [`Ca::run`] for callers that want for example `bool` or [`Bit`]
+    /// cells instead of `u8`.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    pub fn run_as<T: From<Bit>>(&mut self, n: usize) -> Vec<Vec<T>> {
+        let mut res = Vec::with_capacity(n);
+        for _ in 0..n {
+            res.push(self.state.iter().map(|item| T::from(*item)).collect());
+            self.step();
+        }
+        res
+    }
+
+    /// Runs the simulation for the specified number of steps, returning the
+    /// states as `bool`s where `true` is an occupied cell.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    pub fn run_bool(&mut self, n: usize) -> Vec<Vec<bool>> {
+        self.run_as(n)
+    }
+
+    /// Runs the simulation for the specified number of steps, returning the
+    /// states and reporting the progress through `cb`.
+    ///
+    /// `cb` is called with the index of each generation once it has been
+    /// computed, which makes it possible to drive a progress bar for long
+    /// simulations.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    /// * `cb` - The callback to report progress to.
+    pub fn run_with_progress<F: FnMut(usize)>(&mut self, n: usize, mut cb: F) -> Vec<Vec<u8>> {
+        let mut res = Vec::with_capacity(n);
+        for i in 0..n {
+            res.push(self.state());
+            self.step();
+            cb(i);
+        }
+        res
+    }
+
+    /// Runs the simulation for the specified number of steps, returning only
+    /// every `every`-th state starting with the seed.
+    ///
+    /// All steps are still simulated, so the dynamics are the same as with
+    /// [`Ca::run`], but the history is decimated which helps long runs fit on
+    /// the screen.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    /// * `every` - The interval between the returned states.
+    ///
+    /// # Panics
+    /// Panics if `every` is 0.
+    pub fn run_sampled(&mut self, n: usize, every: usize) -> Vec<Vec<u8>> {
+        assert!(every > 0, "sampling interval must be greater than 0");
+        let mut res = Vec::with_capacity(n.div_ceil(every));
+        for i in 0..n {
+            if i % every == 0 {
+                res.push(self.state());
+            }
+            self.step();
+        }
+        res
+    }
+
+    /// Runs the simulation for `n` steps, returning `count` states spread
+    /// evenly over the run, starting with the seed.
+    ///
+    /// The states returned are the ones at steps `i * n / count` for `i` in
+    /// `0..count`, so unlike [`Ca::run_sampled`] exactly `count` states are
+    /// returned even when `count` doesn't divide `n`. If `count` is larger
+    /// than `n` some states are repeated.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    /// * `count` - The number of states to return.
+    pub fn run_sampled_evenly(&mut self, n: usize, count: usize) -> Vec<Vec<u8>> {
+        let mut res = Vec::with_capacity(count);
+        let mut step = 0;
+        for i in 0..count {
+            let target = (i as u128 * n as u128 / count as u128) as usize;
+            while step < target {
+                self.step();
+                step += 1;
+            }
+            res.push(self.state());
+        }
+        // Finish the run like the other run functions.
+        while step < n {
+            self.step();
+            step += 1;
+        }
+        res
+    }
+
+    /// Runs the simulation for at most `max` steps, for as long as `pred`
+    /// holds for the current state.
+    ///
+    /// Returns the states `pred` held for. The state `pred` failed for, if any,
+    /// is left as the current state of the automaton.
+    ///
+    /// # Arguments
+    /// * `max` - The maximum number of steps to run the simulation.
+    /// * `pred` - The condition to check for each state, receiving the cells
+    ///   as 0's and 1's.
+    pub fn run_while<F: Fn(&[u8]) -> bool>(&mut self, max: usize, pred: F) -> Vec<Vec<u8>> {
+        let mut res = Vec::new();
+        for _ in 0..max {
+            let state = self.state();
+            if !pred(&state) {
+                break;
+            }
+            res.push(state);
+            self.step();
+        }
+        res
+    }
+
+    /// Runs the simulation for at most `max` steps, stopping as soon as all
+    /// cells are dead.
+    ///
+    /// Returns the states, including the first dead one, together with the
+    /// generation the automaton died in, or `None` if it survived all `max`
+    /// steps. For rules mapping `000` to 0 with wrapping or zero boundaries a
+    /// dead state stays dead, so nothing is lost by stopping there.
+    ///
+    /// # Arguments
+    /// * `max` - The maximum number of steps to run the simulation.
+    pub fn run_until_dead(&mut self, max: usize) -> (Vec<Vec<u8>>, Option<usize>) {
+        let mut res = Vec::new();
+        for i in 0..max {
+            res.push(self.state());
+            if self.state.iter().all(|item| u8::from(*item) == 0) {
+                return (res, Some(i));
+            }
+            self.step();
+        }
+        (res, None)
+    }
+
+    /// Runs the simulation for at most `max` steps, stopping once the
+    /// population has settled.
+    ///
+    /// Returns the first generation from which the fraction of ON cells stays
+    /// within `tol` over the next `window` generations, i.e. the largest and
+    /// smallest fraction in the window differ by at most `tol`. Returns `None`
+    /// if no such window fits in the first `max` generations.
+    ///
+    /// # Arguments
+    /// * `tol` - The largest allowed change of the fraction of ON cells.
+    /// * `window` - The number of generations the fraction has to stay within
+    ///   `tol`, at least 1.
+    /// * `max` - The maximum number of steps to run the simulation.
+    pub fn mixing_time(&mut self, tol: f64, window: usize, max: usize) -> Option<usize> {
+        let window = window.max(1);
+        let width = self.width().max(1) as f64;
+        let mut recent = VecDeque::with_capacity(window);
+        for i in 0..max {
+            if recent.len() == window {
+                recent.pop_front();
+            }
+            recent.push_back(analysis::population(&self.state()) as f64 / width);
+            if recent.len() == window {
+                let lo = recent.iter().cloned().fold(f64::INFINITY, f64::min);
+                let hi = recent.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                if hi - lo <= tol {
+                    return Some(i + 1 - window);
+                }
+            }
+            self.step();
+        }
+        None
+    }
+
+    /// Runs the simulation for the specified number of steps, storing the
+    /// states in `out`.
+    ///
+    /// Any previous contents of `out` are replaced, but its allocations are
+    /// reused, which avoids allocating a new history for repeated runs.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    /// * `out` - The buffer to store the states in.
+    pub fn run_into(&mut self, n: usize, out: &mut Vec<Vec<u8>>) {
+        out.resize_with(n, Vec::new);
+        for row in out.iter_mut() {
+            row.clear();
+            row.extend(self.state.iter().map(|item| u8::from(*item)));
+            self.step();
+        }
+    }
+
+    /// Runs many independent automata, returning the states of each.
+    ///
+    /// Each configuration is a `(seed, rule, steps)` triple run like
+    /// `Ca::new(seed, rule).run(steps)`, which is convenient for parameter
+    /// sweeps and galleries.
+    ///
+    /// # Arguments
+    /// * `configs` - The seed, rule and number of steps of each automaton.
+    pub fn run_batch(configs: &[(Vec<u8>, u8, usize)]) -> Vec<Vec<Vec<u8>>> {
+        configs
+            .iter()
+            .map(|(seed, rule, steps)| Ca::new(seed.clone(), *rule).run(*steps))
+            .collect()
+    }
+
+    /// Runs `rule` from `seed` on an infinite line, returning `steps` states
+    /// padded to a common width.
+    ///
+    /// The seed is surrounded by a uniform background, which evolves under
+    /// the rule as well, so the region that can differ from the background
+    /// grows by one cell on each side every step. Each state is centered and
+    /// padded with the background of its generation to the width of the last
+    /// one, `seed.len() + 2 * (steps - 1)`, which gives for example the full
+    /// triangle of rule 90 from a single cell without guessing the width.
+    ///
+    /// # Arguments
+    /// * `seed` - The starting point of the simulation, surrounded by OFF
+    ///   cells.
+    /// * `rule` - The rule to use.
+    /// * `steps` - The number of states to return.
+    pub fn run_unbounded(seed: Vec<u8>, rule: u8, steps: usize) -> Vec<Vec<u8>> {
+        let table = RuleTable::new(rule);
+        let mut state: Vec<Bit> = seed.iter().map(|item| Bit::from(*item)).collect();
+        let mut background = Bit::Zero;
+        let mut res = Vec::with_capacity(steps);
+        for _ in 0..steps {
+            res.push((state.clone(), background));
+            // Two background cells on each side give the next state of the
+            // cells just outside the current region, the ends are dropped.
+            let mut padded = vec![background; 2];
+            padded.extend_from_slice(&state);
+            padded.extend_from_slice(&[background; 2]);
+            let next = table.apply(&padded, BoundaryCondition::Zero);
+            state = next[1..next.len() - 1].to_vec();
+            background = table.get(background, background, background);
+        }
+
+        let width = res.last().map_or(0, |(state, _)| state.len());
+        res.into_iter()
+            .map(|(state, background)| {
+                let pad = vec![u8::from(background); (width - state.len()) / 2];
+                let mut row = pad.clone();
+                row.extend(state.iter().map(|item| u8::from(*item)));
+                row.extend(pad);
+                row
+            })
+            .collect()
+    }
+
+    /// Runs `rule` from a random seed and summarizes its behaviour.
+    ///
+    /// # Arguments
+    /// * `rule` - The rule to use.
+    /// * `width` - The width of the automaton.
+    /// * `steps` - The number of steps to run the simulation.
+    /// * `density` - The probability of a cell being ON in the random seed.
+    /// * `rng` - The random number generator used for the seed.
+    pub fn summary<R: Rng>(
+        rule: u8,
+        width: usize,
+        steps: usize,
+        density: f64,
+        rng: &mut R,
+    ) -> analysis::RuleSummary {
+        let mut ca = Ca::new(seed::random_seed(width, density, rng), rule);
+        analysis::RuleSummary::from_generations(rule, &ca.run(steps))
+    }
+
+    /// Runs the simulation for `n` steps, writing each generation to `w` as a
+    /// line of ASCII as soon as it is computed.
+    ///
+    /// The writer is flushed after every line so that the output appears
+    /// incrementally, even for long runs.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    /// * `w` - The writer to render the generations into.
+    pub fn stream_ascii<W: Write>(&mut self, n: usize, w: &mut W) -> io::Result<()> {
+        for _ in 0..n {
+            writeln!(w, "{}", TermImage::draw_ascii_row(&self.state()))?;
+            w.flush()?;
+            self.step();
+        }
+        Ok(())
+    }
+
+    /// Runs the simulation for `n` steps, writing each generation to `w` as a
+    /// JSON array on its own line, e.g. `[0,1,0,1]`.
+    ///
+    /// Like [`Ca::stream_ascii`] the writer is flushed after every line.
+    ///
+    /// # Arguments
+    /// * `n` - The number of steps to run the simulation.
+    /// * `w` - The writer to render the generations into.
+    pub fn stream_jsonl<W: Write>(&mut self, n: usize, w: &mut W) -> io::Result<()> {
+        for _ in 0..n {
+            let cells: Vec<String> = self
+                .state
+                .iter()
+                .map(|b| u8::from(*b).to_string())
+                .collect();
+            writeln!(w, "[{}]", cells.join(","))?;
+            w.flush()?;
+            self.step();
+        }
+        Ok(())
+    }
+
+    /// Counts how often each cell is ON across many runs from random seeds.
+    ///
+    /// Each trial runs `rule` for `steps` steps from a fresh random seed and
+    /// adds 1 to the counter of every (generation, cell) position that is ON.
+    /// The result holds `steps` rows of `width` counters, so memory use grows
+    /// as `width * steps * 4` bytes regardless of the number of trials.
+    ///
+    /// # Arguments
+    /// * `rule` - The rule to use.
+    /// * `width` - The width of the automaton.
+    /// * `trials` - The number of runs to accumulate.
+    /// * `density` - The probability of a cell being ON in the random seeds.
+    /// * `steps` - The number of steps in each run.
+    /// * `rng` - The random number generator used for the seeds.
+    pub fn heatmap<R: Rng>(
+        rule: u8,
+        width: usize,
+        trials: usize,
+        density: f64,
+        steps: usize,
+        rng: &mut R,
+    ) -> Vec<Vec<u32>> {
+        let mut counts = vec![vec![0; width]; steps];
+        for _ in 0..trials {
+            let mut ca = Ca::new(seed::random_seed(width, density, rng), rule);
+            for (row, gen) in counts.iter_mut().zip(ca.run(steps)) {
+                for (count, cell) in row.iter_mut().zip(gen) {
+                    *count += u32::from(cell);
+                }
+            }
+        }
+        counts
+    }
+}
+
+impl PartialEq for Ca {
+    fn eq(&self, other: &Ca) -> bool {
+        // The seeds are left out, automata reaching the same state from
+        // different seeds evolve identically from there.
+        self.state == other.state
+            && self.rules == other.rules
+            && self.boundary == other.boundary
+            && self.current_boundary() == other.current_boundary()
+            && self.background == other.background
+    }
+}
+
+impl Hash for Ca {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.state.hash(state);
+        self.rule().hash(state);
+        self.boundary.hash(state);
+        self.current_boundary().hash(state);
+        self.background.hash(state);
+    }
+}
+
+/// An infinite iterator over the generations of a `Ca`, see [`Ca::iter`].
+pub struct Generations<'a> {
+    ca: &'a mut Ca,
+}
+
+impl Iterator for Generations<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let state = self.ca.state();
+        self.ca.step();
+        Some(state)
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Ca {
+    type Item = Vec<u8>;
+    type IntoIter = Generations<'a>;
+
+    fn into_iter(self) -> Generations<'a> {
+        self.iter()
+    }
+}
+
+/// An infinite iterator over the generations of a `Ca` it owns, see
+/// `Ca::into_iter`.
+pub struct IntoGenerations {
+    ca: Ca,
+}
+
+impl Iterator for IntoGenerations {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let state = self.ca.state();
+        self.ca.step();
+        Some(state)
+    }
+}
+
+/// Consumes the automaton, yielding its generations starting with the
+/// current state.
+///
+/// Like [`Ca::iter`] the iterator never ends, so a `for` loop over it has to
+/// `break` or the iterator has to be bounded with for example `take`.
+impl IntoIterator for Ca {
+    type Item = Vec<u8>;
+    type IntoIter = IntoGenerations;
+
+    fn into_iter(self) -> IntoGenerations {
+        IntoGenerations { ca: self }
+    }
+}
+
+/// Parses a rule given either as a number or as a binary string like
+/// `0b01101110`.
+fn parse_rule_number(s: &str) -> Result<u8, CaError> {
+    match s.strip_prefix("0b") {
+        Some(bin) => u8::from_str_radix(bin, 2),
+        None => s.parse(),
+    }
+    .map_err(|_| CaError::InvalidRuleString(String::from(s)))
+}
+
+impl FromStr for Ca {
+    type Err = CaError;
+
+    /// Parses an automaton from a compact specification.
+    ///
+    /// Two forms are accepted, the short `RULE:CELLS`, for example
+    /// `"110:00100"`, and a list of `key=value` pairs separated by `;`, for
+    /// example `"rule=110;width=100;seed=center;boundary=zero"`. The keys are
+    ///
+    /// * `rule` - The rule, as a number or binary string (required).
+    /// * `seed` - Either the cells as a string of 0's and 1's or `center` for a
+    ///   single ON cell in the middle (defaults to `center`).
+    /// * `width` - The width of the automaton. Required for a `center` seed,
+    ///   and tiles the cells of the seed otherwise.
+    /// * `boundary` - One of `wrap`, `zero`, `one` or `reflect` (defaults to
+    ///   `wrap`).
+    fn from_str(s: &str) -> Result<Ca, CaError> {
+        if !s.contains('=') {
+            let mut parts = s.splitn(2, ':');
+            let rule = parse_rule_number(parts.next().unwrap_or("").trim())?;
+            let cells = parts.next().ok_or_else(|| {
+                CaError::InvalidSpec(String::from("expected a spec like 110:00100"))
+            })?;
+            let seed = seed::from_pattern_strict(cells.trim())?;
+            if seed.is_empty() {
+                return Err(CaError::EmptyData("seed"));
+            }
+            return Ok(Ca::new(seed, rule));
+        }
+
+        let (mut rule, mut width, mut seed, mut boundary) = (None, None, None, None);
+        for pair in s.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let mut kv = pair.splitn(2, '=');
+            let key = kv.next().unwrap_or("").trim();
+            let value = kv
+                .next()
+                .ok_or_else(|| CaError::InvalidSpec(format!("expected key=value, got '{}'", pair)))?
+                .trim();
+            match key {
+                "rule" => rule = Some(parse_rule_number(value)?),
+                "width" => {
+                    width =
+                        Some(value.parse::<usize>().map_err(|_| {
+                            CaError::InvalidSpec(format!("invalid width '{}'", value))
+                        })?)
+                }
+                "seed" => seed = Some(value),
+                "boundary" => boundary = Some(value.parse()?),
+                _ => return Err(CaError::InvalidSpec(format!("unknown key '{}'", key))),
+            }
+        }
+
+        let rule = rule.ok_or_else(|| CaError::InvalidSpec(String::from("missing rule")))?;
+        let seed = match (seed.unwrap_or("center"), width) {
+            ("center", Some(width)) if width > 0 => {
+                let mut res = vec![0; width];
+                res[width / 2] = 1;
+                res
+            }
+            ("center", _) => {
+                return Err(CaError::InvalidSpec(String::from(
+                    "a center seed needs a non-zero width",
+                )))
+            }
+            (cells, Some(width)) => seed::tile_seed(&seed::from_pattern_strict(cells)?, width)?,
+            (cells, None) => seed::from_pattern_strict(cells)?,
+        };
+        if seed.is_empty() {
+            return Err(CaError::EmptyData("seed"));
+        }
+        Ok(Ca::with_boundary(
+            seed,
+            rule,
+            boundary.unwrap_or(BoundaryCondition::Wrap),
+        ))
+    }
+}
+
+/// A terminal color escape sequence.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TermColor {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    /// A 24-bit color, for terminals supporting truecolor.
+    Rgb(u8, u8, u8),
+    Reset,
+}
+
+impl TermColor {
+    fn to_fg(self) -> String {
+        match self {
+            TermColor::Black => String::from("\x1b[30m"),
+            TermColor::Red => String::from("\x1b[31m"),
+            TermColor::Green => String::from("\x1b[32m"),
+            TermColor::Yellow => String::from("\x1b[33m"),
+            TermColor::Blue => String::from("\x1b[34m"),
+            TermColor::Magenta => String::from("\x1b[35m"),
+            TermColor::Cyan => String::from("\x1b[36m"),
+            TermColor::White => String::from("\x1b[37m"),
+            TermColor::Rgb(r, g, b) => format!("\x1b[38;2;{};{};{}m", r, g, b),
+            TermColor::Reset => String::from("\x1b[0m"),
+        }
+    }
+
+    fn to_bg(self) -> String {
+        match self {
+            TermColor::Black => String::from("\x1b[40m"),
+            TermColor::Red => String::from("\x1b[41m"),
+            TermColor::Green => String::from("\x1b[42m"),
+            TermColor::Yellow => String::from("\x1b[43m"),
+            TermColor::Blue => String::from("\x1b[44m"),
+            TermColor::Magenta => String::from("\x1b[45m"),
+            TermColor::Cyan => String::from("\x1b[46m"),
+            TermColor::White => String::from("\x1b[47m"),
+            TermColor::Rgb(r, g, b) => format!("\x1b[48;2;{};{};{}m", r, g, b),
+            TermColor::Reset => String::from("\x1b[0m"),
+        }
+    }
+}
+
+impl FromStr for TermColor {
+    type Err = CaError;
+
+    /// Parses the lowercase name of a color, for example `"red"`, or a hex
+    /// color like `"#ff8000"`.
+    fn from_str(s: &str) -> Result<TermColor, CaError> {
+        if let Some(hex) = s.strip_prefix('#') {
+            let invalid = || CaError::InvalidSpec(format!("invalid hex color '{}'", s));
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|c| u8::from_str_radix(c, 16).ok())
+                    .ok_or_else(invalid)
+            };
+            if hex.len() != 6 {
+                return Err(invalid());
+            }
+            return Ok(TermColor::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        match s {
+            "black" => Ok(TermColor::Black),
+            "red" => Ok(TermColor::Red),
+            "green" => Ok(TermColor::Green),
+            "yellow" => Ok(TermColor::Yellow),
+            "blue" => Ok(TermColor::Blue),
+            "magenta" => Ok(TermColor::Magenta),
+            "cyan" => Ok(TermColor::Cyan),
+            "white" => Ok(TermColor::White),
+            "reset" => Ok(TermColor::Reset),
+            _ => Err(CaError::InvalidSpec(format!("unknown color '{}'", s))),
+        }
+    }
+}
+
+impl fmt::Display for TermColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_fg())
+    }
+}
+
+/// The ways of drawing a `TermImage` in the terminal.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Renderer {
+    /// One ASCII character per cell, see [`TermImage::draw_ascii`].
+    Ascii,
+    /// One HALF BLOCK per 2x1 cells, see [`TermImage::draw_unicode`].
+    Unicode,
+    /// One braille symbol per 4x2 cells, see [`TermImage::draw_braille`].
+    Braille,
+}
+
+/// The vertical position of the first row (the seed) in an aligned image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VerticalAlign {
+    Top,
+    Center,
+    Bottom,
+}
+
+/// The bit of a braille symbol, starting at U+2800, set for each cell of the
+/// 4 rows by 2 columns covered by the symbol.
+pub type BrailleDots = [[u8; 2]; 4];
+
+/// The standard Unicode braille dot numbering, where the dots of the left
+/// column are 1, 2, 3 and 7 and of the right column 4, 5, 6 and 8. See
+/// <https://en.wikipedia.org/wiki/Braille_Patterns>.
+pub const BRAILLE_STANDARD: BrailleDots = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// An alternate braille dot numbering going left to right and then top to
+/// bottom, used by some terminals and fonts.
+pub const BRAILLE_ROW_MAJOR: BrailleDots = [[0x01, 0x02], [0x04, 0x08], [0x10, 0x20], [0x40, 0x80]];
+
+/// The line ending used by the text renderers of a `TermImage`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LineEnding {
+    /// `\n`, the default.
+    Lf,
+    /// `\r\n`, as expected by some Windows tools.
+    CrLf,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// An overlay drawn on top of a `TermImage`, see [`TermImage::add_layer`].
+pub struct Layer {
+    /// The 1-bit overlay, where values >0 are interpreted as ON.
+    pub data: Vec<Vec<u8>>,
+    /// The color of the ON cells of the overlay.
+    pub color: TermColor,
+}
+
+/// A terminal 1-bit character image.
+pub struct TermImage {
+    data: Vec<Vec<u8>>,
+    comment: Option<String>,
+    line_ending: LineEnding,
+    layers: Vec<Layer>,
+}
+
+impl TermImage {
+    /// Creates a new `TermImage` from the given `data`.
+    ///
+    /// Rows shorter than the longest one are padded with OFF cells, so ragged
+    /// data, for example from [`Ca::run_unbounded`], can be rendered.
+    ///
+    /// # Arguments
+    /// * `data` - The 1-bit image where values >1 are interpreted as ON and 0 is
+    ///     OFF.
+    #[allow(clippy::doc_overindented_list_items)]
+    pub fn new(mut data: Vec<Vec<u8>>) -> TermImage {
+        let width = data.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in data.iter_mut().filter(|row| row.len() < width) {
+            row.resize(width, 0);
+        }
+        TermImage {
+            data,
+            comment: None,
+            line_ending: LineEnding::Lf,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Creates a new `TermImage` where the values greater than or equal to
+    /// `threshold` are interpreted as ON.
+    ///
+    /// `TermImage::new` corresponds to a `threshold` of 1.
+    ///
+    /// # Arguments
+    /// * `data` - The image data.
+    /// * `threshold` - The smallest value interpreted as ON.
+    pub fn with_threshold(data: Vec<Vec<u8>>, threshold: u8) -> TermImage {
+        let data = data
+            .into_iter()
+            .map(|row| apply_threshold(row, threshold))
+            .collect();
+        TermImage::new(data)
+    }
+
+    /// Sets a comment, like the parameters used to create the image, that is
+    /// embedded in the file formats supporting it.
+    ///
+    /// PNG files store it in a `tEXt` chunk and SVG files in an XML comment.
+    ///
+    /// # Arguments
+    /// * `comment` - The comment to embed.
+    pub fn set_comment(&mut self, comment: &str) {
+        self.comment = Some(String::from(comment));
+    }
+
+    /// Sets the line ending used by the text renderers, `\n` by default.
+    ///
+    /// # Arguments
+    /// * `ending` - The line ending to use.
+    pub fn set_line_ending(&mut self, ending: LineEnding) {
+        self.line_ending = ending;
+    }
+
+    /// Inverts the image so that ON cells become OFF and vice versa.
+    ///
+    /// Any value greater than 0 becomes 0 and 0 becomes 1, which means that
+    /// the colors passed to the renderers are effectively swapped.
+    pub fn invert(&mut self) {
+        for row in self.data.iter_mut() {
+            for el in row.iter_mut() {
+                *el = if *el > 0 { 0 } else { 1 };
+            }
+        }
+    }
+
+    /// Mirrors the image horizontally by reversing every row.
+    pub fn flip_horizontal(&mut self) {
+        let layers = self.layers.iter_mut().map(|layer| &mut layer.data);
+        for row in iter::once(&mut self.data).chain(layers).flatten() {
+            row.reverse();
+        }
+    }
+
+    /// Returns a new `TermImage` with the rows and columns swapped.
+    ///
+    /// This rotates a run so that time goes from left to right and space from
+    /// top to bottom. The width of the result is the width of the first row.
+    pub fn transpose(&self) -> TermImage {
+        self.derive(|data| {
+            let width = data.first().map_or(0, |row| row.len());
+            (0..width)
+                .map(|col| data.iter().map(|row| row[col]).collect())
+                .collect()
+        })
+    }
+
+    /// Returns a new `TermImage` containing only the given region.
+    ///
+    /// Ranges reaching outside of the image are clamped to its bounds, so
+    /// cropping with `0..usize::MAX` returns a copy of the whole image.
+    ///
+    /// # Arguments
+    /// * `rows` - The rows (generations) to keep.
+    /// * `cols` - The columns (cells) to keep.
+    pub fn crop(&self, rows: Range<usize>, cols: Range<usize>) -> TermImage {
+        self.derive(|data| {
+            let row_end = rows.end.min(data.len());
+            let row_start = rows.start.min(row_end);
+            data[row_start..row_end]
+                .iter()
+                .map(|row| {
+                    let col_end = cols.end.min(row.len());
+                    let col_start = cols.start.min(col_end);
+                    row[col_start..col_end].to_vec()
+                })
+                .collect()
+        })
+    }
+
+    /// Returns a new `TermImage` of exactly `height` rows with the first row
+    /// placed according to `align`.
+    ///
+    /// Rows above the first row are filled with OFF cells, and rows that
+    /// don't fit below it are dropped. If the image is too short to reach the
+    /// bottom it is padded with OFF cells there as well.
+    ///
+    /// # Arguments
+    /// * `height` - The number of rows in the resulting image.
+    /// * `align` - Where to place the first row.
+    pub fn align(&self, height: usize, align: VerticalAlign) -> TermImage {
+        let offset = match align {
+            VerticalAlign::Top => 0,
+            VerticalAlign::Center => height / 2,
+            VerticalAlign::Bottom => height.saturating_sub(1),
+        };
+        self.derive(|data| {
+            let width = data.first().map_or(0, |row| row.len());
+            let mut res = vec![vec![0; width]; offset];
+            res.extend(data.iter().take(height - offset).cloned());
+            res.resize(height, vec![0; width]);
+            res
+        })
+    }
+
+    /// Returns a new `TermImage` with `f` applied to the data of the image
+    /// and of every layer, keeping the other settings.
+    fn derive<F: Fn(&[Vec<u8>]) -> Vec<Vec<u8>>>(&self, f: F) -> TermImage {
+        TermImage {
+            data: f(&self.data),
+            comment: self.comment.clone(),
+            line_ending: self.line_ending,
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| Layer {
+                    data: f(&layer.data),
+                    color: layer.color,
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds an overlay drawn on top of the image and any previously added
+    /// layers by [`TermImage::draw_layered`].
+    ///
+    /// Layers follow the image through transformations like
+    /// [`TermImage::crop`], but only the image itself is inverted by
+    /// [`TermImage::invert`].
+    ///
+    /// # Arguments
+    /// * `layer` - The overlay, with the same dimensions as the image.
+    ///
+    /// # Errors
+    /// Returns an error if the layer doesn't have the same dimensions as the
+    /// image.
+    pub fn add_layer(&mut self, layer: Layer) -> Result<(), CaError> {
+        if layer.data.len() != self.data.len()
+            || self
+                .data
+                .iter()
+                .zip(&layer.data)
+                .any(|(a, b)| a.len() != b.len())
+        {
+            return Err(CaError::DimensionMismatch);
+        }
+        self.layers.push(layer);
+        Ok(())
+    }
+
+    /// Render the 1-bit image using unicode HALF BLOCKS into a `String`.
+    ///
+    /// Each symbol covers 2 rows, so a trailing odd row is left out.
+    ///
+    /// # Arguments
+    /// * `fg` - The foreground color to use.
+    /// * `bg` - The background color to use.
+    pub fn draw_unicode(&self, fg: TermColor, bg: TermColor) -> String {
+        render_unicode_with(&self.data, fg, bg, false, self.line_ending)
+    }
+
+    /// Render the image like [`TermImage::draw_unicode`], but with every odd
+    /// output row shifted right by a leading space in the background color.
+    ///
+    /// Offsetting alternate rows by half a symbol gives a brick or hexagonal
+    /// look, which can make fractal patterns easier to follow.
+    ///
+    /// # Arguments
+    /// * `fg` - The foreground color to use.
+    /// * `bg` - The background color to use.
+    /// * `stagger` - Whether to shift the odd rows, `false` is the same as
+    ///   [`TermImage::draw_unicode`].
+    pub fn draw_unicode_staggered(&self, fg: TermColor, bg: TermColor, stagger: bool) -> String {
+        render_unicode_with(&self.data, fg, bg, stagger, self.line_ending)
+    }
+
+    /// Render the 1-bit image using unicode block glyphs into a `String`,
+    /// without any background color.
+    ///
+    /// Each symbol covers 2 rows and is picked from `▀`, `▄`, `█` and ` `
+    /// depending on which of the cells are ON, which works in terminals (or
+    /// logs) where background colors are unreliable. A trailing odd row is
+    /// left out.
+    ///
+    /// # Arguments
+    /// * `fg` - The color to draw the ON cells with.
+    pub fn draw_unicode_glyphs(&self, fg: TermColor) -> String {
+        let mut res = String::new();
+        for i in (0..self.data.len().saturating_sub(1)).step_by(2) {
+            res.push_str(&fg.to_fg());
+            res.push_str(&half_blocks(&self.data[i], &self.data[i + 1]));
+            res.push_str(&format!(
+                "{}{}",
+                TermColor::Reset,
+                self.line_ending.as_str()
+            ));
+        }
+        res
+    }
+
+    /// Render the image with `renderer` but without any ANSI escape
+    /// sequences, for terminals without color support or for piping.
+    ///
+    /// The unicode renderer uses the glyphs of
+    /// [`TermImage::draw_unicode_glyphs`] and the braille renderer draws the
+    /// ON cells as dots, the ASCII renderer is unchanged.
+    ///
+    /// # Arguments
+    /// * `renderer` - The renderer to draw the image with.
+    pub fn draw_plain(&self, renderer: Renderer) -> String {
+        match renderer {
+            Renderer::Ascii => self.draw_ascii(),
+            Renderer::Unicode => {
+                let mut res = String::new();
+                for i in (0..self.data.len().saturating_sub(1)).step_by(2) {
+                    res.push_str(&half_blocks(&self.data[i], &self.data[i + 1]));
+                    res.push_str(self.line_ending.as_str());
+                }
+                res
+            }
+            Renderer::Braille => {
+                render_braille_dots(&self.data, &BRAILLE_STANDARD, self.line_ending)
+            }
+        }
+    }
+
+    /// Render the image using unicode HALF BLOCKS into a `String`, coloring
+    /// each cell by its value.
+    ///
+    /// A cell with value `v` is drawn with `palette[v]`, values past the end of
+    /// the palette use its last color. This is useful for images with more
+    /// than two kinds of cells, like the output of [`combine_generations`]
+    /// with [`CombineOp::Diff`]. Nothing is drawn with an empty palette.
+    ///
+    /// # Arguments
+    /// * `palette` - The colors to use for each value.
+    pub fn draw_unicode_palette(&self, palette: &[TermColor]) -> String {
+        if palette.is_empty() {
+            return String::new();
+        }
+        let color = |v: u8| &palette[(v as usize).min(palette.len() - 1)];
+        let mut res = String::new();
+        for i in (0..self.data.len().saturating_sub(1)).step_by(2) {
+            for (top, bottom) in self.data[i].iter().zip(self.data[i + 1].iter()) {
+                res.push_str(&format!(
+                    "{}{}▄{}",
+                    color(*top).to_bg(),
+                    color(*bottom).to_fg(),
+                    TermColor::Reset
+                ));
+            }
+            res.push_str(self.line_ending.as_str());
+        }
+        res
+    }
+
+    /// Render the image and its layers using unicode HALF BLOCKS into a
+    /// `String`.
+    ///
+    /// The layers are composited from the last added down to the image, so
+    /// the color of a cell is the color of the topmost layer where it's ON.
+    /// Cells that are OFF in every layer are drawn with `fg` if ON in the
+    /// image and `bg` otherwise. A trailing odd row is left out.
+    ///
+    /// # Arguments
+    /// * `fg` - The color of the ON cells of the image.
+    /// * `bg` - The color of the OFF cells.
+    pub fn draw_layered(&self, fg: TermColor, bg: TermColor) -> String {
+        let color = |i: usize, j: usize| {
+            self.layers
+                .iter()
+                .rev()
+                .find(|layer| layer.data[i][j] > 0)
+                .map_or(if self.data[i][j] > 0 { fg } else { bg }, |layer| {
+                    layer.color
+                })
+        };
+        let mut res = String::new();
+        for i in (0..self.data.len().saturating_sub(1)).step_by(2) {
+            for j in 0..self.data[i].len().min(self.data[i + 1].len()) {
+                res.push_str(&format!(
+                    "{}{}▄{}",
+                    color(i, j).to_bg(),
+                    color(i + 1, j).to_fg(),
+                    TermColor::Reset
+                ));
+            }
+            res.push_str(self.line_ending.as_str());
+        }
+        res
+    }
+
+    /// Render the cells where two 1-bit images agree and differ using
+    /// unicode HALF BLOCKS into a `String`.
+    ///
+    /// Cells with the same state in both images are drawn with `same` and
+    /// cells that differ with `diff`, which shows how for example a single
+    /// flipped seed cell propagates. A trailing odd row is left out.
+    ///
+    /// # Arguments
+    /// * `a` - The first image.
+    /// * `b` - The second image.
+    /// * `same` - The color of cells that agree, typically a dim color.
+    /// * `diff` - The color of cells that differ, typically a bright color.
+    ///
+    /// # Errors
+    /// Returns an error if the images don't have the same dimensions.
+    pub fn draw_diff(
+        a: &TermImage,
+        b: &TermImage,
+        same: TermColor,
+        diff: TermColor,
+    ) -> Result<String, CaError> {
+        let data = combine_generations(&a.data, &b.data, CombineOp::Xor)?;
+        let mut image = TermImage::new(data);
+        image.set_line_ending(a.line_ending);
+        Ok(image.draw_unicode_palette(&[same, diff]))
+    }
+
+    /// Render the 1-bit image using unicode braille symbols into a `String`.
+    ///
+    /// Each symbol covers 4 rows and 2 columns, any remaining rows or columns
+    /// are left out. Images with fewer than 4 rows render as an empty string.
+    ///
+    /// # Arguments
+    /// * `fg` - The foreground color to use.
+    /// * `bg` - The background color to use.
+    pub fn draw_braille(&self, fg: TermColor, bg: TermColor) -> String {
+        self.draw_braille_mapped(fg, bg, &BRAILLE_STANDARD)
+    }
+
+    /// Render the 1-bit image using unicode braille symbols into a `String`,
+    /// with a custom assignment of cells to braille dots.
+    ///
+    /// [`TermImage::draw_braille`] uses the standard Unicode dot numbering,
+    /// [`BRAILLE_STANDARD`]. Use for example [`BRAILLE_ROW_MAJOR`] for
+    /// terminals or fonts that map the bits of the symbols differently.
+    ///
+    /// # Arguments
+    /// * `fg` - The foreground color to use.
+    /// * `bg` - The background color to use.
+    /// * `dots` - The bit of the symbol for each cell of the 4x2 block.
+    pub fn draw_braille_mapped(&self, fg: TermColor, bg: TermColor, dots: &BrailleDots) -> String {
+        render_braille_with(&self.data, fg, bg, dots, self.line_ending)
+    }
+
+    /// Render only the rows `top_row..top_row + height` of the image into a
+    /// `String`, for scrolling through a tall image in a fixed viewport.
+    ///
+    /// The range is clamped to the rows of the image and `height` counts
+    /// rows of cells, not lines of output, so a viewport of 8 rows is 4 lines
+    /// with [`Renderer::Unicode`] and 2 with [`Renderer::Braille`]. The colors
+    /// are ignored by [`Renderer::Ascii`].
+    ///
+    /// # Arguments
+    /// * `top_row` - The first row to render.
+    /// * `height` - The number of rows to render.
+    /// * `renderer` - The renderer to use.
+    /// * `fg` - The foreground color to use.
+    /// * `bg` - The background color to use.
+    pub fn draw_viewport(
+        &self,
+        top_row: usize,
+        height: usize,
+        renderer: Renderer,
+        fg: TermColor,
+        bg: TermColor,
+    ) -> String {
+        let start = top_row.min(self.data.len());
+        let end = top_row.saturating_add(height).min(self.data.len());
+        let rows = &self.data[start..end];
+        match renderer {
+            Renderer::Ascii => render_ascii_with(rows, 1, self.line_ending),
+            Renderer::Unicode => render_unicode_with(rows, fg, bg, false, self.line_ending),
+            Renderer::Braille => {
+                render_braille_with(rows, fg, bg, &BRAILLE_STANDARD, self.line_ending)
+            }
+        }
+    }
+
+    /// Render the 1-bit image using only ASCII symbols into a `String`.
+    pub fn draw_ascii(&self) -> String {
+        self.draw_ascii_scaled(1)
+    }
+
+    /// Render the 1-bit image using only ASCII symbols into a `String`, with
+    /// every cell repeated `cell_width` times horizontally.
+    ///
+    /// Characters in most terminal fonts are about twice as tall as they are
+    /// wide, so a `cell_width` of 2 gives roughly square cells.
+    ///
+    /// # Arguments
+    /// * `cell_width` - The number of characters per cell.
+    pub fn draw_ascii_scaled(&self, cell_width: usize) -> String {
+        render_ascii_with(&self.data, cell_width, self.line_ending)
+    }
+
+    /// Render the image as text where the glyph of each cell is picked by the
+    /// pattern it forms with its left and right neighbours in the same row.
+    ///
+    /// The pattern is indexed like a rule, `left << 2 | center << 1 | right`,
+    /// so `glyphs[0]` is used for an OFF cell with OFF neighbours and
+    /// `glyphs[7]` for three ON cells in a row. Rows wrap at the edges.
+    ///
+    /// # Arguments
+    /// * `glyphs` - The glyph for each of the 8 neighbourhood patterns.
+    pub fn draw_textured(&self, glyphs: [char; 8]) -> String {
+        let mut res = String::new();
+        for row in &self.data {
+            let len = row.len();
+            for i in 0..len {
+                let left = (row[(i + len - 1) % len] > 0) as usize;
+                let center = (row[i] > 0) as usize;
+                let right = (row[(i + 1) % len] > 0) as usize;
+                res.push(glyphs[left << 2 | center << 1 | right]);
+            }
+            res.push_str(self.line_ending.as_str());
+        }
+        res
+    }
+
+    /// Encodes the image as an RGBA PNG with one pixel per cell.
+    ///
+    /// Use an `off` color with an alpha of 0 to make OFF cells transparent,
+    /// for example when overlaying the image on something else.
+    ///
+    /// # Arguments
+    /// * `on` - The RGBA color of ON cells.
+    /// * `off` - The RGBA color of OFF cells.
+    pub fn to_png(&self, on: [u8; 4], off: [u8; 4]) -> Vec<u8> {
+        self.to_png_downscaled(on, off, 1)
+    }
+
+    /// Encodes the image as an RGBA PNG with one pixel per `factor` x
+    /// `factor` block of cells.
+    ///
+    /// Each pixel gets a color between `off` and `on` depending on the
+    /// fraction of ON cells in its block, which gives smooth thumbnails of
+    /// large images instead of the moiré patterns of skipping cells. Blocks
+    /// at the right and bottom edges may be smaller than `factor` x `factor`.
+    ///
+    /// # Arguments
+    /// * `on` - The RGBA color of ON cells.
+    /// * `off` - The RGBA color of OFF cells.
+    /// * `factor` - The number of cells along each side of a pixel, a factor
+    ///   of 0 is treated as 1.
+    ///
+    /// PNG images can't be empty, so an image without any cells is encoded
+    /// as a single pixel in the `off` color.
+    pub fn to_png_downscaled(&self, on: [u8; 4], off: [u8; 4], factor: usize) -> Vec<u8> {
+        let factor = factor.max(1);
+        let width = self.data.first().map_or(0, |row| row.len());
+        if width == 0 {
+            return export::encode_png(1, 1, &off, self.comment.as_deref());
+        }
+        let out_width = width.div_ceil(factor);
+        let out_height = self.data.len().div_ceil(factor);
+        let mut pixels = Vec::with_capacity(out_width * out_height * 4);
+        for rows in self.data.chunks(factor) {
+            for x in (0..width).step_by(factor) {
+                let (mut ons, mut total) = (0, 0);
+                for row in rows {
+                    for el in row.iter().skip(x).take(factor) {
+                        total += 1;
+                        if *el > 0 {
+                            ons += 1;
+                        }
+                    }
+                }
+                let fraction = if total > 0 {
+                    f64::from(ons) / f64::from(total)
+                } else {
+                    0.
+                };
+                for (on, off) in on.iter().zip(off.iter()) {
+                    let channel = f64::from(*off) + (f64::from(*on) - f64::from(*off)) * fraction;
+                    pixels.push(channel.round() as u8);
+                }
+            }
+        }
+        export::encode_png(out_width, out_height, &pixels, self.comment.as_deref())
+    }
+
+    /// Renders the image as an SVG document with a 1x1 square per cell.
+    ///
+    /// If `off` is `None` no squares are drawn for OFF cells, leaving them
+    /// transparent.
+    ///
+    /// # Arguments
+    /// * `on` - The fill color of ON cells, for example `"#000000"`.
+    /// * `off` - The fill color of OFF cells, if any.
+    pub fn to_svg(&self, on: &str, off: Option<&str>) -> String {
+        let width = self.data.first().map_or(0, |row| row.len());
+        let height = self.data.len();
+        let mut res = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" shape-rendering=\"crispEdges\">\n",
+            w = width,
+            h = height
+        );
+        if let Some(comment) = &self.comment {
+            res.push_str(&format!("<!-- {} -->\n", xml_comment_text(comment)));
+        }
+        if let Some(off) = off {
+            res.push_str(&format!(
+                "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                width, height, off
+            ));
+        }
+        for (y, row) in self.data.iter().enumerate() {
+            for (x, el) in row.iter().enumerate() {
+                if *el > 0 {
+                    res.push_str(&format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"{}\"/>\n",
+                        x, y, on
+                    ));
+                }
+            }
+        }
+        res.push_str("</svg>\n");
+        res
+    }
+
+    /// Renders the image as an XBM bitmap, C source declaring the
+    /// `<name>_width` and `<name>_height` defines and the `<name>_bits` array.
+    ///
+    /// Every row is padded to a whole number of bytes and the cells are packed
+    /// least significant bit first, with ON cells as set bits.
+    ///
+    /// # Arguments
+    /// * `name` - The prefix of the declared identifiers.
+    pub fn to_xbm(&self, name: &str) -> String {
+        let width = self.data.first().map_or(0, |row| row.len());
+        let mut bytes = Vec::new();
+        for row in &self.data {
+            for chunk in row.chunks(8) {
+                let byte = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |acc, (i, el)| acc | (((*el > 0) as u8) << i));
+                bytes.push(format!("0x{:02x}", byte));
+            }
+        }
+        let mut res = format!(
+            "#define {n}_width {}\n#define {n}_height {}\nstatic char {n}_bits[] = {{\n",
+            width,
+            self.data.len(),
+            n = name
+        );
+        for line in bytes.chunks(12) {
+            res.push_str(&format!("   {},\n", line.join(", ")));
+        }
+        // Drop the comma after the last byte.
+        if !bytes.is_empty() {
+            res.truncate(res.len() - 2);
+            res.push('\n');
+        }
+        res.push_str("};\n");
+        res
+    }
+
+    /// Returns the image as a grid of characters without any escape sequences.
+    ///
+    /// This is useful for embedding the image in other terminal interfaces
+    /// that apply their own styling to each cell.
+    ///
+    /// # Arguments
+    /// * `on` - The character to use for ON cells.
+    /// * `off` - The character to use for OFF cells.
+    pub fn to_char_grid(&self, on: char, off: char) -> Vec<Vec<char>> {
+        self.data
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|el| if *el > 0 { on } else { off })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Render the 1-bit image using only ASCII symbols into a `String`, with
+    /// each row prefixed by its generation number.
+    ///
+    /// The numbers are zero-padded to the width of the largest one so that the
+    /// rows stay aligned, for example `07 | ..#..`.
+    pub fn draw_ascii_numbered(&self) -> String {
+        let digits = self.data.len().saturating_sub(1).to_string().len();
+        let mut res = String::new();
+        for (i, row) in self.data.iter().enumerate() {
+            res.push_str(&format!(
+                "{:0width$} | {}{}",
+                i,
+                TermImage::draw_ascii_row(row),
+                self.line_ending.as_str(),
+                width = digits
+            ));
+        }
+        res
+    }
+
+    /// Render a single row of a 1-bit image using only ASCII symbols into a
+    /// `String`, without a trailing newline.
+    ///
+    /// # Arguments
+    /// * `row` - The row to render, where values >0 are interpreted as ON.
+    pub fn draw_ascii_row(row: &[u8]) -> String {
+        row.iter()
+            .map(|el| if *el > 0 { '#' } else { '.' })
+            .collect()
+    }
+
+    /// Render a profile of values between 0 and 1, like the output of
+    /// [`analysis::density_profile`], as a bar chart into a `String`.
+    ///
+    /// Each value is drawn as a column of `#` rising from the bottom of
+    /// `height` rows, rounded to the nearest row and clamped to the chart.
+    /// Rows end with `\n`, see [`TermImage::draw_profile_with`] for other
+    /// line endings.
+    ///
+    /// # Arguments
+    /// * `profile` - The values to draw, one per column.
+    /// * `height` - The number of rows of the chart.
+    pub fn draw_profile(profile: &[f64], height: usize) -> String {
+        TermImage::draw_profile_with(profile, height, LineEnding::Lf)
+    }
+
+    /// Render a profile like [`TermImage::draw_profile`], ending each row
+    /// with `ending`.
+    ///
+    /// # Arguments
+    /// * `profile` - The values to draw, one per column.
+    /// * `height` - The number of rows of the chart.
+    /// * `ending` - The line ending to use.
+    pub fn draw_profile_with(profile: &[f64], height: usize, ending: LineEnding) -> String {
+        let bars: Vec<usize> = profile
+            .iter()
+            .map(|v| (v.clamp(0., 1.) * height as f64).round() as usize)
+            .collect();
+        let mut res = String::new();
+        for level in (1..=height).rev() {
+            for bar in bars.iter() {
+                res.push(if *bar >= level { '#' } else { ' ' });
+            }
+            res.push_str(ending.as_str());
+        }
+        res
+    }
+
+    /// Render a grid of counts, like the output of [`Ca::heatmap`], into a
+    /// `String` using one character of a density ramp per count.
+    ///
+    /// Counts are normalized by `max` and clamped, so 0 is drawn with the
+    /// first character of `ramp` and `max` or more with the last. An empty
+    /// ramp renders nothing. Rows end with `\n`, see
+    /// [`TermImage::draw_grayscale_with`] for other line endings.
+    ///
+    /// # Arguments
+    /// * `counts` - The rows of counts to render.
+    /// * `max` - The count drawn with the last character of the ramp.
+    /// * `ramp` - The characters to use from lowest to highest density, for
+    ///   example `" .:-=+*#%@"`.
+    pub fn draw_grayscale(counts: &[Vec<u32>], max: u32, ramp: &[char]) -> String {
+        TermImage::draw_grayscale_with(counts, max, ramp, LineEnding::Lf)
+    }
+
+    /// Render a grid of counts like [`TermImage::draw_grayscale`], ending
+    /// each row with `ending`.
+    ///
+    /// # Arguments
+    /// * `counts` - The rows of counts to render.
+    /// * `max` - The count drawn with the last character of the ramp.
+    /// * `ramp` - The characters to use from lowest to highest density.
+    /// * `ending` - The line ending to use.
+    pub fn draw_grayscale_with(
+        counts: &[Vec<u32>],
+        max: u32,
+        ramp: &[char],
+        ending: LineEnding,
+    ) -> String {
+        if ramp.is_empty() {
+            return String::new();
+        }
+        let last = ramp.len() - 1;
+        let mut res = String::new();
+        for row in counts {
+            for &count in row {
+                let level = if max == 0 {
+                    0
+                } else {
+                    let fraction = count.min(max) as f64 / max as f64;
+                    (fraction * last as f64).round() as usize
+                };
+                res.push(ramp[level]);
+            }
+            res.push_str(ending.as_str());
+        }
+        res
+    }
+}
+
+/// Render 1-bit `data` using unicode HALF BLOCKS into a `String`, like
+/// [`TermImage::draw_unicode`] without creating a `TermImage`.
+///
+/// # Arguments
+/// * `data` - The image, where values >0 are interpreted as ON.
+/// * `fg` - The foreground color to use.
+/// * `bg` - The background color to use.
+pub fn render_unicode(data: &[Vec<u8>], fg: TermColor, bg: TermColor) -> String {
+    render_unicode_with(data, fg, bg, false, LineEnding::Lf)
+}
+
+/// Render 1-bit `data` using unicode braille symbols into a `String`, like
+/// [`TermImage::draw_braille`] without creating a `TermImage`.
+///
+/// # Arguments
+/// * `data` - The image, where values >0 are interpreted as ON.
+/// * `fg` - The foreground color to use.
+/// * `bg` - The background color to use.
+pub fn render_braille(data: &[Vec<u8>], fg: TermColor, bg: TermColor) -> String {
+    render_braille_with(data, fg, bg, &BRAILLE_STANDARD, LineEnding::Lf)
+}
+
+/// Render 1-bit `data` using only ASCII symbols into a `String`, like
+/// [`TermImage::draw_ascii`] without creating a `TermImage`.
+///
+/// # Arguments
+/// * `data` - The image, where values >0 are interpreted as ON.
+pub fn render_ascii(data: &[Vec<u8>]) -> String {
+    render_ascii_with(data, 1, LineEnding::Lf)
+}
+
+/// Returns the classic picture of `rule` from *A New Kind of Science*, a
+/// single ON cell on an infinite OFF background, as an image.
+///
+/// The image is `2 * steps - 1` cells wide with the seed in the middle column,
+/// so the whole light cone of the seed is visible, see [`Ca::run_unbounded`].
+/// Its comment names the rule.
+///
+/// # Arguments
+/// * `rule` - The rule to draw.
+/// * `steps` - The number of generations to draw, including the seed.
+pub fn nks_figure(rule: u8, steps: usize) -> TermImage {
+    let mut image = TermImage::new(Ca::run_unbounded(vec![1], rule, steps));
+    image.set_comment(&rule_name(rule));
+    image
+}
+
+/// Returns `text` made safe to embed in an XML comment, which can't contain
+/// `--` or end with `-`.
+fn xml_comment_text(text: &str) -> String {
+    // Replacing once leaves dashes behind in runs of three or more.
+    let mut text = text.to_string();
+    while text.contains("--") {
+        text = text.replace("--", "- -");
+    }
+    if text.ends_with('-') {
+        text.push(' ');
+    }
+    text
+}
+
+/// Returns `data` with the rows shorter than the longest one padded with OFF
+/// cells, borrowing it if all rows already have the same length.
+fn pad_rows(data: &[Vec<u8>]) -> Cow<'_, [Vec<u8>]> {
+    let width = data.iter().map(|row| row.len()).max().unwrap_or(0);
+    if data.iter().all(|row| row.len() == width) {
+        return Cow::Borrowed(data);
+    }
+    Cow::Owned(
+        data.iter()
+            .map(|row| {
+                let mut row = row.clone();
+                row.resize(width, 0);
+                row
+            })
+            .collect(),
+    )
+}
+
+fn render_unicode_with(
+    data: &[Vec<u8>],
+    fg: TermColor,
+    bg: TermColor,
+    stagger: bool,
+    ending: LineEnding,
+) -> String {
+    let data = pad_rows(data);
+    let mut res = String::new();
+    for i in (0..data.len().saturating_sub(1)).step_by(2) {
+        // Every output row covers two rows of data.
+        if stagger && i % 4 == 2 {
+            res.push_str(&format!("{} {}", bg.to_bg(), TermColor::Reset));
+        }
+        for (top, bottom) in data[i].iter().zip(data[i + 1].iter()) {
+            let top_color = if *top > 0 { fg.to_bg() } else { bg.to_bg() };
+            let bottom_color = if *bottom > 0 { fg.to_fg() } else { bg.to_fg() };
+
+            res.push_str(&format!(
+                "{}{}▄{}",
+                top_color,
+                bottom_color,
+                TermColor::Reset
+            ));
+        }
+        res.push_str(ending.as_str());
+    }
+    res
+}
+
+fn render_braille_with(
+    data: &[Vec<u8>],
+    fg: TermColor,
+    bg: TermColor,
+    dots: &BrailleDots,
+    ending: LineEnding,
+) -> String {
+    if data.len() < 4 {
+        return String::new();
+    }
+    format!(
+        "{}{}{}{}",
+        fg.to_fg(),
+        bg.to_bg(),
+        render_braille_dots(data, dots, ending),
+        TermColor::Reset
+    )
+}
+
+/// Renders the braille symbols of `data` without any colors.
+fn render_braille_dots(data: &[Vec<u8>], dots: &BrailleDots, ending: LineEnding) -> String {
+    if data.len() < 4 {
+        return String::new();
+    }
+    let data = pad_rows(data);
+    let mut res = String::new();
+    // Iterate over 4x2 blocks of data for each braille symbol
+    for i in (0..data.len() - 3).step_by(4) {
+        for j in (0..data[i].len().saturating_sub(1)).step_by(2) {
+            // Each dot has its own bit that when added yields the symbol with
+            // it included.
+            let mut codepoint = 0x2800;
+            for (di, row) in dots.iter().enumerate() {
+                for (dj, bit) in row.iter().enumerate() {
+                    if data[i + di][j + dj] > 0 {
+                        codepoint += u32::from(*bit);
+                    }
+                }
+            }
+
+            res.push(char::from_u32(codepoint).expect("Invalid braille codepoint"));
+        }
+        res.push_str(ending.as_str());
+    }
+    res
+}
+
+/// Returns the half block glyphs covering the cells of `top` and `bottom`.
+fn half_blocks(top: &[u8], bottom: &[u8]) -> String {
+    top.iter()
+        .zip(bottom.iter())
+        .map(|(top, bottom)| match (*top > 0, *bottom > 0) {
+            (true, true) => '█',
+            (true, false) => '▀',
+            (false, true) => '▄',
+            (false, false) => ' ',
+        })
+        .collect()
+}
+
+fn render_ascii_with(data: &[Vec<u8>], cell_width: usize, ending: LineEnding) -> String {
+    let mut res = String::new();
+    for row in pad_rows(data).iter() {
+        for c in TermImage::draw_ascii_row(row).chars() {
+            for _ in 0..cell_width {
+                res.push(c);
+            }
+        }
+        res.push_str(ending.as_str());
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashSet};
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_ca_error_variants() {
+        use seed::{
+            from_pattern, random_seed_weighted, seed_from_pnm, single_cell_seed, tile_seed,
+        };
+
+        assert_eq!(Err(CaError::EmptyData("pattern")), tile_seed(&[], 3));
+        assert_eq!(
+            Err(CaError::SeedTooShort {
+                width: 2,
+                required: 3
+            }),
+            tile_seed(&[0, 1, 0], 2)
+        );
+        assert_eq!(
+            Err(CaError::OutOfBounds { index: 4, width: 4 }),
+            single_cell_seed(4, 4)
+        );
+        assert_eq!(Err(CaError::InvalidPattern('x')), from_pattern("01x"));
+        assert_eq!(
+            Err(CaError::InvalidRuleString(String::from("300"))),
+            "300:010".parse::<Ca>().map(|_| ())
+        );
+        assert_eq!(
+            Err(CaError::InvalidNeighborhood(8)),
+            RuleTable::from_u128(0, 8)
+        );
+        assert_eq!(
+            Err(CaError::RuleTooLarge {
+                rule: 256,
+                neighborhood_size: 3
+            }),
+            RuleTable::from_u128(256, 3)
+        );
+        assert_eq!(
+            Err(CaError::TieBreakRequired(4)),
+            RuleTable::majority(4, None)
+        );
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            Err(CaError::InvalidProbability(1.5)),
+            random_seed_weighted(&[1.5], &mut rng)
+        );
+        assert_eq!(
+            Err(CaError::DimensionMismatch),
+            combine_generations(&[vec![1]], &[vec![1, 0]], CombineOp::Or)
+        );
+        assert!(matches!(
+            seed_from_pnm(b"P1 1 1 1", 128),
+            Err(CaError::InvalidImage(_))
+        ));
+        #[cfg(feature = "image")]
+        assert!(matches!(
+            seed::seed_from_image("/nonexistent.pgm", 128),
+            Err(CaError::Io(_))
+        ));
+        assert!(matches!(
+            "purple".parse::<TermColor>(),
+            Err(CaError::InvalidSpec(_))
+        ));
+        assert!(matches!(
+            Ca::with_background(vec![1], 110, &[]),
+            Err(CaError::EmptyData("background"))
+        ));
+    }
+
+    #[test]
+    fn test_ca_error_display() {
+        assert_eq!(
+            "seed can't be empty",
+            CaError::EmptyData("seed").to_string()
+        );
+        assert_eq!(
+            "invalid cell '2' in seed, expected 0 or 1",
+            CaError::InvalidPattern('2').to_string()
+        );
+        assert_eq!(
+            "unknown boundary condition 'edge'",
+            "edge".parse::<BoundaryCondition>().unwrap_err().to_string()
+        );
+        let err: Box<dyn std::error::Error> = Box::new(CaError::DimensionMismatch);
+        assert_eq!("the dimensions don't match", err.to_string());
+    }
+
+    #[test]
+    fn test_rule_name_round_trip() {
+        assert_eq!("Rule 110", rule_name(110));
+        for rule in [0, 30, 90, 110, 255] {
+            assert_eq!(Ok(rule), parse_rule_name(&rule_name(rule)));
+        }
+        assert_eq!(Ok(30), parse_rule_name("rule 30"));
+        assert_eq!(Ok(30), parse_rule_name("RULE30"));
+    }
+
+    #[test]
+    fn test_parse_rule_name_invalid() {
+        for name in [
+            "Rule", "Rule 256", "Rule -1", "Rule +1", "Rul 30", "30", "Rule 3 0", "",
+        ] {
+            assert!(parse_rule_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_rule_array_const() {
+        const TABLE: [Bit; 8] = rule_array(90);
+        use Bit::{One, Zero};
+        assert_eq!([Zero, One, Zero, One, One, Zero, One, Zero], TABLE);
+        assert_eq!(RuleTable::new(90), RuleTable::from(TABLE));
+        for rule in 0..=255 {
+            assert_eq!(RuleTable::new(rule), RuleTable::from(rule_array(rule)));
+        }
+    }
+
+    #[test]
+    #[allow(clippy::useless_format)]
+    fn test_rule_table_keys() {
+        let r = RuleTable::new(0);
+        let mut keys: Vec<String> = r.table.keys().map(|k| format!("{}", k)).collect();
+        keys.sort();
+        assert_eq!(
+            vec![
+                String::from("000"),
+                String::from("001"),
+                String::from("010"),
+                String::from("011"),
+                String::from("100"),
+                String::from("101"),
+                String::from("110"),
+                String::from("111")
+            ],
+            keys
+        );
+    }
+
+    #[test]
+    fn test_rule_table_to_binary_rule_90() {
+        let r = RuleTable::new(90);
+        // Use the BTreeMap to order the elements by keys.
+        let table: BTreeMap<String, Bit> = r.table.iter().map(|(k, v)| (k.clone(), *v)).collect();
         let values: Vec<u8> = table.values().map(|v| (*v).into()).collect();
         assert_eq!(vec![0, 1, 0, 1, 1, 0, 1, 0], values);
     }
 
     #[test]
-    fn test_rule_table_to_binary_rule_110() {
-        let r = RuleTable::new(110);
-        // Use the BTreeMap to order the elements by keys.
-        let table: BTreeMap<String, Bit> = r.table.iter().map(|(k, v)| (k.clone(), *v)).collect();
-        let values: Vec<u8> = table.values().map(|v| (*v).into()).collect();
-        assert_eq!(vec![0, 1, 1, 1, 0, 1, 1, 0], values);
+    fn test_rule_table_to_binary_rule_110() {
+        let r = RuleTable::new(110);
+        // Use the BTreeMap to order the elements by keys.
+        let table: BTreeMap<String, Bit> = r.table.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        let values: Vec<u8> = table.values().map(|v| (*v).into()).collect();
+        assert_eq!(vec![0, 1, 1, 1, 0, 1, 1, 0], values);
+    }
+
+    #[test]
+    fn test_rule_table_from_u128_matches_u8() {
+        for &rule in &[0, 30, 90, 110, 255] {
+            assert_eq!(
+                Ok(RuleTable::new(rule)),
+                RuleTable::from_u128(u128::from(rule), 3)
+            );
+        }
+        assert_eq!(3, RuleTable::new(110).neighborhood_size());
+    }
+
+    #[test]
+    fn test_rule_table_from_u128_larger_neighborhood() {
+        let r = RuleTable::from_u128(1 << 31, 5).unwrap();
+        assert_eq!(5, r.neighborhood_size());
+        assert_eq!(32, r.table.len());
+        assert_eq!(Bit::One, r.table["11111"]);
+        assert_eq!(Bit::Zero, r.table["11110"]);
+
+        let r = RuleTable::from_u128(u128::MAX, 7).unwrap();
+        assert_eq!(128, r.table.len());
+        assert!(r.table.values().all(|b| *b == Bit::One));
+    }
+
+    #[test]
+    fn test_rule_table_apply_matches_step() {
+        let seed = vec![0, 1, 1, 0, 1, 0, 0, 0, 1, 1];
+        let table = RuleTable::new(90);
+        let mut state: Vec<Bit> = seed.iter().map(|&c| Bit::from(c)).collect();
+        let mut ca = Ca::new(seed, 90);
+        for _ in 0..8 {
+            state = table.apply(&state, BoundaryCondition::Wrap);
+            ca.step();
+            assert_eq!(
+                ca.state(),
+                state.iter().map(|&b| u8::from(b)).collect::<Vec<u8>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rule_table_majority() {
+        assert_eq!(RuleTable::new(232), RuleTable::majority(3, None).unwrap());
+        // Ties can't happen in odd neighbourhoods.
+        assert_eq!(
+            RuleTable::new(232),
+            RuleTable::majority(3, Some(TieBreak::Alive)).unwrap()
+        );
+        let five = RuleTable::majority(5, None).unwrap();
+        assert_eq!(Bit::One, five.table["11001"]);
+        assert_eq!(Bit::Zero, five.table["10001"]);
+    }
+
+    #[test]
+    fn test_rule_table_majority_ties() {
+        // Patterns with two live cells are tied, the "center" is index 2.
+        let expected = [
+            (TieBreak::Stay, [0, 0, 1]),
+            (TieBreak::Flip, [1, 1, 0]),
+            (TieBreak::Alive, [1, 1, 1]),
+            (TieBreak::Dead, [0, 0, 0]),
+        ];
+        for (tie, [p1001, p0101, p0110]) in expected {
+            let table = RuleTable::majority(4, Some(tie)).unwrap();
+            assert_eq!(Bit::from(p1001), table.table["1001"], "{:?}", tie);
+            assert_eq!(Bit::from(p0101), table.table["0101"], "{:?}", tie);
+            assert_eq!(Bit::from(p0110), table.table["0110"], "{:?}", tie);
+            assert_eq!(Bit::One, table.table["1101"]);
+            assert_eq!(Bit::Zero, table.table["0100"]);
+        }
+    }
+
+    #[test]
+    fn test_rule_table_threshold() {
+        // At least one live cell is rule 254, all three is rule 128.
+        assert_eq!(
+            RuleTable::new(254),
+            RuleTable::threshold(3, 1, None).unwrap()
+        );
+        assert_eq!(
+            RuleTable::new(128),
+            RuleTable::threshold(3, 3, None).unwrap()
+        );
+        // Only the patterns with exactly one live cell use the tie break.
+        assert_eq!(
+            RuleTable::new(232),
+            RuleTable::threshold(3, 1, Some(TieBreak::Dead)).unwrap()
+        );
+        assert_eq!(
+            RuleTable::new(236),
+            RuleTable::threshold(3, 1, Some(TieBreak::Stay)).unwrap()
+        );
+
+        let table = RuleTable::threshold(5, 2, Some(TieBreak::Flip)).unwrap();
+        assert_eq!(Bit::One, table.table["10001"]);
+        assert_eq!(Bit::Zero, table.table["10100"]);
+        assert_eq!(Bit::One, table.table["11100"]);
+        assert_eq!(Bit::Zero, table.table["00001"]);
+        assert_eq!(
+            RuleTable::from_u128(u32::MAX as u128, 5).unwrap(),
+            RuleTable::threshold(5, 0, None).unwrap()
+        );
+        assert_eq!(
+            RuleTable::from_u128(0, 5).unwrap(),
+            RuleTable::threshold(5, 6, None).unwrap()
+        );
+        assert!(RuleTable::threshold(8, 2, None).is_err());
+    }
+
+    #[test]
+    fn test_rule_table_majority_invalid() {
+        assert!(RuleTable::majority(4, None).is_err());
+        assert!(RuleTable::majority(0, Some(TieBreak::Stay)).is_err());
+        assert!(RuleTable::majority(8, Some(TieBreak::Stay)).is_err());
+    }
+
+    #[test]
+    fn test_rule_table_majority_step() {
+        let table = RuleTable::majority(3, None).unwrap();
+        let state: Vec<Bit> = [1, 1, 0, 1, 0, 0, 0, 1]
+            .iter()
+            .map(|&c| Bit::from(c))
+            .collect();
+        let next: Vec<u8> = table
+            .apply(&state, BoundaryCondition::Zero)
+            .into_iter()
+            .map(u8::from)
+            .collect();
+        assert_eq!(vec![1, 1, 1, 0, 0, 0, 0, 0], next);
+    }
+
+    #[test]
+    fn test_rule_table_rule_number() {
+        for rule in [0, 30, 90, 110, 255] {
+            assert_eq!(rule as u128, RuleTable::new(rule).rule_number());
+        }
+        for rule in [0, 1 << 31 | 1, u128::MAX] {
+            assert_eq!(rule, RuleTable::from_u128(rule, 7).unwrap().rule_number());
+        }
+    }
+
+    #[test]
+    fn test_rule_table_from_u128_invalid() {
+        assert!(RuleTable::from_u128(256, 3).is_err());
+        assert!(RuleTable::from_u128(0, 0).is_err());
+        assert!(RuleTable::from_u128(0, 8).is_err());
+    }
+
+    #[test]
+    fn test_rule_table_all() {
+        let all = RuleTable::all();
+        assert_eq!(RuleTable::new(90), all[90]);
+        assert_eq!(RuleTable::new(0), all[0]);
+        assert_eq!(RuleTable::new(255), all[255]);
+        assert!(std::ptr::eq(all, RuleTable::all()));
+    }
+
+    #[test]
+    fn test_rule_table_string_rule_90() {
+        assert_eq!(
+            "| 000 | 001 | 010 | 011 | 100 | 101 | 110 | 111 |\n\
+             |  0  |  1  |  0  |  1  |  1  |  0  |  1  |  0  |\n",
+            rule_table_string(90)
+        );
+    }
+
+    #[test]
+    fn test_rule_table_display_rule_30() {
+        assert_eq!(
+            "| 000 | 001 | 010 | 011 | 100 | 101 | 110 | 111 |\n\
+             |  0  |  1  |  1  |  1  |  1  |  0  |  0  |  0  |\n",
+            RuleTable::new(30).to_string()
+        );
+    }
+
+    #[test]
+    fn test_rule_table_to_table_string_5_bits() {
+        let table = RuleTable::from_u128(1 << 31 | 1, 5)
+            .unwrap()
+            .to_table_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(2, lines.len());
+        assert_eq!(lines[0].len(), lines[1].len());
+        assert!(lines[0].starts_with("| 00000 | 00001 |"));
+        assert!(lines[0].ends_with("| 11111 |"));
+        assert!(lines[1].starts_with("|   1   |   0   |"));
+        assert!(lines[1].ends_with("|   1   |"));
+        assert_eq!(33, lines[1].matches('|').count());
+    }
+
+    #[test]
+    fn test_rule_table_string_rule_110() {
+        let table = rule_table_string(110);
+        assert_eq!(
+            "|  0  |  1  |  1  |  1  |  0  |  1  |  1  |  0  |",
+            table.lines().nth(1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rule_to_dot() {
+        let dot = rule_to_dot(110);
+        assert!(dot.starts_with("digraph rule110 {\n"));
+        assert!(dot.ends_with("}\n"));
+        let nodes = dot.lines().filter(|l| l.ends_with("\";")).count();
+        let edges = dot.lines().filter(|l| l.contains("->")).count();
+        assert_eq!((4, 8), (nodes, edges));
+        assert!(dot.contains("\"01\" -> \"11\" [label=\"011/1\"];"));
+        assert!(dot.contains("\"11\" -> \"11\" [label=\"111/0\"];"));
+    }
+
+    #[test]
+    fn test_mirror_rule() {
+        assert_eq!(124, mirror_rule(110));
+        assert_eq!(86, mirror_rule(30));
+        assert_eq!(90, mirror_rule(90));
+    }
+
+    #[test]
+    fn test_complement_rule() {
+        assert_eq!(137, complement_rule(110));
+        assert_eq!(135, complement_rule(30));
+        assert_eq!(110, complement_rule(complement_rule(110)));
+    }
+
+    #[test]
+    fn test_canonical_rule_classes() {
+        for &rule in &[110, 124, 137, 193] {
+            assert_eq!(110, canonical_rule(rule));
+        }
+        for &rule in &[30, 86, 135, 149] {
+            assert_eq!(30, canonical_rule(rule));
+        }
+        let mut classes: Vec<u8> = (0..=255).map(canonical_rule).collect();
+        classes.sort();
+        classes.dedup();
+        assert_eq!(88, classes.len());
+    }
+
+    #[test]
+    fn test_ca_step() {
+        let mut ca = Ca::new(vec![0, 0, 1, 0, 0], 90);
+        ca.step();
+        let state: Vec<u8> = ca.state.iter().map(|v| (*v).into()).collect();
+        assert_eq!(vec![0, 1, 0, 1, 0], state);
+    }
+
+    #[test]
+    fn test_heatmap_deterministic() {
+        let a = Ca::heatmap(30, 16, 10, 0.5, 8, &mut StdRng::seed_from_u64(42));
+        let b = Ca::heatmap(30, 16, 10, 0.5, 8, &mut StdRng::seed_from_u64(42));
+        assert_eq!(a, b);
+        assert_eq!(8, a.len());
+        assert!(a.iter().all(|row| row.len() == 16));
+        assert!(a.iter().flatten().all(|&count| count <= 10));
+    }
+
+    #[test]
+    fn test_heatmap_identity_rule() {
+        // Rule 204 keeps every cell unchanged, so a full seed stays full.
+        let counts = Ca::heatmap(204, 4, 3, 1.0, 2, &mut StdRng::seed_from_u64(0));
+        assert_eq!(vec![vec![3; 4]; 2], counts);
+    }
+
+    #[test]
+    fn test_draw_viewport() {
+        let image = TermImage::new(Ca::new(vec![0, 0, 0, 0, 1, 0, 0, 0, 0], 30).run(16));
+        let (fg, bg) = (TermColor::White, TermColor::Black);
+
+        let full = image.draw_ascii();
+        let lines: Vec<&str> = full.lines().collect();
+        let expected: String = lines[3..8].iter().map(|l| format!("{}\n", l)).collect();
+        assert_eq!(expected, image.draw_viewport(3, 5, Renderer::Ascii, fg, bg));
+
+        let full = image.draw_unicode(fg, bg);
+        let lines: Vec<&str> = full.lines().collect();
+        let expected: String = lines[2..5].iter().map(|l| format!("{}\n", l)).collect();
+        assert_eq!(
+            expected,
+            image.draw_viewport(4, 6, Renderer::Unicode, fg, bg)
+        );
+
+        let braille = image.draw_viewport(8, 8, Renderer::Braille, fg, bg);
+        assert_eq!(
+            TermImage::new(image.data[8..16].to_vec()).draw_braille(fg, bg),
+            braille
+        );
+    }
+
+    #[test]
+    fn test_draw_viewport_clamped() {
+        let image = TermImage::new(vec![vec![1, 0], vec![0, 1], vec![1, 1]]);
+        let (fg, bg) = (TermColor::White, TermColor::Black);
+        assert_eq!(
+            ".#\n##\n",
+            image.draw_viewport(1, 100, Renderer::Ascii, fg, bg)
+        );
+        assert_eq!("", image.draw_viewport(5, 2, Renderer::Ascii, fg, bg));
+        assert_eq!(
+            "##\n",
+            image.draw_viewport(2, usize::MAX, Renderer::Ascii, fg, bg)
+        );
+    }
+
+    #[test]
+    fn test_draw_profile() {
+        let profile = [1.0, 0.5, 0.0, 0.74, 2.0, -1.0];
+        assert_eq!("#   # \n## ## \n", TermImage::draw_profile(&profile, 2));
+        assert_eq!("", TermImage::draw_profile(&profile, 0));
+        assert_eq!(
+            "#   # \r\n## ## \r\n",
+            TermImage::draw_profile_with(&profile, 2, LineEnding::CrLf)
+        );
+    }
+
+    #[test]
+    fn test_draw_grayscale() {
+        let ramp: Vec<char> = " .:-=+*#%@".chars().collect();
+        let counts = vec![vec![0, 10, 5], vec![20, 1, 0]];
+        assert_eq!(" @+\n@. \n", TermImage::draw_grayscale(&counts, 10, &ramp));
+        assert_eq!(
+            " @+\r\n@. \r\n",
+            TermImage::draw_grayscale_with(&counts, 10, &ramp, LineEnding::CrLf)
+        );
+    }
+
+    #[test]
+    fn test_draw_grayscale_degenerate() {
+        let counts = vec![vec![0, 3]];
+        assert_eq!("..\n", TermImage::draw_grayscale(&counts, 0, &['.', '#']));
+        assert_eq!("", TermImage::draw_grayscale(&counts, 3, &[]));
+        assert_eq!("", TermImage::draw_grayscale(&[], 3, &['.', '#']));
+    }
+
+    #[test]
+    fn test_apply_rule() {
+        assert_eq!(
+            vec![0, 1, 0, 1, 0],
+            apply_rule(&[0, 0, 1, 0, 0], 90, BoundaryCondition::Wrap)
+        );
+        assert_eq!(
+            vec![1, 0, 0, 0, 1],
+            apply_rule(&[0, 1, 0, 1, 0], 90, BoundaryCondition::Wrap)
+        );
+        assert_eq!(
+            vec![0, 1, 0, 0],
+            apply_rule(&[1, 0, 0, 0], 90, BoundaryCondition::Zero)
+        );
+    }
+
+    #[test]
+    fn test_combine_generations() {
+        let a = vec![vec![0, 0, 1, 1]];
+        let b = vec![vec![0, 1, 0, 5]];
+        let combine = |op| combine_generations(&a, &b, op).unwrap();
+        assert_eq!(vec![vec![0, 1, 1, 1]], combine(CombineOp::Or));
+        assert_eq!(vec![vec![0, 0, 0, 1]], combine(CombineOp::And));
+        assert_eq!(vec![vec![0, 1, 1, 0]], combine(CombineOp::Xor));
+        assert_eq!(vec![vec![0, 2, 1, 3]], combine(CombineOp::Diff));
+    }
+
+    #[test]
+    fn test_combine_generations_mismatched() {
+        let a = vec![vec![0, 1]];
+        assert!(combine_generations(&a, &[vec![0, 1], vec![1, 0]], CombineOp::Or).is_err());
+        assert!(combine_generations(&a, &[vec![0, 1, 1]], CombineOp::Or).is_err());
+    }
+
+    #[test]
+    fn test_ca_from_str_short() {
+        let mut ca: Ca = "90:00100".parse().unwrap();
+        assert_eq!(vec![0, 0, 1, 0, 0], ca.state());
+        ca.step();
+        assert_eq!(vec![0, 1, 0, 1, 0], ca.state());
+    }
+
+    #[test]
+    fn test_ca_from_str_pairs() {
+        let mut ca: Ca = "rule=90;width=5;seed=center".parse().unwrap();
+        assert_eq!(vec![0, 0, 1, 0, 0], ca.state());
+        ca.step();
+        assert_eq!(vec![0, 1, 0, 1, 0], ca.state());
+
+        let mut ca: Ca = "rule=0b01011010; seed=1000; boundary=zero".parse().unwrap();
+        ca.step();
+        assert_eq!(vec![0, 1, 0, 0], ca.state());
+
+        let ca: Ca = "rule=90;width=5;seed=01".parse().unwrap();
+        assert_eq!(vec![0, 1, 0, 1, 0], ca.state());
+    }
+
+    #[test]
+    fn test_ca_from_str_malformed() {
+        for spec in &[
+            "",
+            "90",
+            "90:",
+            "256:010",
+            "90:0120",
+            "seed=010",
+            "rule=90;seed=center",
+            "rule=90;width=x",
+            "rule=90;color=red",
+            "rule=90;seed=010;boundary=twisted",
+            "rule=90;width",
+        ] {
+            assert!(spec.parse::<Ca>().is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_ca_step_async() {
+        let seed = vec![0, 1, 1, 0, 1, 0, 0, 1, 1, 1];
+        let mut ca = Ca::new(seed.clone(), 110);
+        ca.step_async(0., &mut StdRng::seed_from_u64(1));
+        assert_eq!(seed, ca.state());
+
+        let mut ca = Ca::new(seed.clone(), 110);
+        ca.step_async(1., &mut StdRng::seed_from_u64(1));
+        let mut sync = Ca::new(seed.clone(), 110);
+        sync.step();
+        assert_eq!(sync.state(), ca.state());
+
+        let run = |rng_seed| {
+            let mut ca = Ca::new(seed.clone(), 110);
+            let mut rng = StdRng::seed_from_u64(rng_seed);
+            (0..20)
+                .map(|_| {
+                    ca.step_async(0.5, &mut rng);
+                    ca.state()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(7), run(7));
+    }
+
+    #[test]
+    fn test_ca_run_bool() {
+        let gens = Ca::new(vec![0, 0, 1, 0, 0], 90).run_bool(3);
+        assert_eq!(
+            vec![
+                vec![false, false, true, false, false],
+                vec![false, true, false, true, false],
+                vec![true, false, false, false, true],
+            ],
+            gens
+        );
+    }
+
+    #[test]
+    fn test_ca_run_as_matches_run() {
+        let seed = vec![1, 0, 1, 1, 0, 0, 1, 0];
+        let expected = Ca::new(seed.clone(), 110).run(12);
+        assert_eq!(expected, Ca::new(seed.clone(), 110).run_as::<u8>(12));
+        let bits = Ca::new(seed.clone(), 110).run_as::<Bit>(12);
+        let bools = Ca::new(seed, 110).run_as::<bool>(12);
+        for ((row, bit_row), bool_row) in expected.iter().zip(bits).zip(bools) {
+            assert_eq!(row, &bit_row.into_iter().map(u8::from).collect::<Vec<u8>>());
+            assert_eq!(
+                row,
+                &bool_row.into_iter().map(u8::from).collect::<Vec<u8>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_ca_run_batch() {
+        let configs = vec![
+            (vec![0, 0, 1, 0, 0], 90, 4),
+            (vec![1, 0, 1, 1, 0, 0, 1], 110, 10),
+            (vec![1], 30, 0),
+        ];
+        let batch = Ca::run_batch(&configs);
+        assert_eq!(3, batch.len());
+        for ((seed, rule, steps), gens) in configs.into_iter().zip(batch) {
+            assert_eq!(Ca::new(seed, rule).run(steps), gens);
+        }
+        assert!(Ca::run_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_ca_run_unbounded() {
+        let image = TermImage::new(Ca::run_unbounded(vec![1], 90, 4));
+        assert_eq!("...#...\n..#.#..\n.#...#.\n#.#.#.#\n", image.draw_ascii());
+
+        let gens = Ca::run_unbounded(vec![1, 0, 1], 30, 10);
+        assert_eq!(10, gens.len());
+        assert!(gens.iter().all(|row| row.len() == 21));
+        assert_eq!(Vec::<Vec<u8>>::new(), Ca::run_unbounded(vec![1], 90, 0));
+    }
+
+    #[test]
+    fn test_nks_figure() {
+        let steps = 16;
+        let image = nks_figure(90, steps);
+        assert_eq!(Some("Rule 90"), image.comment.as_deref());
+        assert_eq!(steps, image.data.len());
+        let center = steps - 1;
+        for (i, row) in image.data.iter().enumerate() {
+            assert_eq!(2 * steps - 1, row.len());
+            // The pattern fills the triangle below the seed, with its edges
+            // ON and nothing outside of it.
+            assert_eq!(1, row[center - i]);
+            assert_eq!(1, row[center + i]);
+            assert!(row
+                .iter()
+                .enumerate()
+                .all(|(j, el)| *el == 0 || (center - i..=center + i).contains(&j)));
+        }
+        assert!(nks_figure(90, 0).data.is_empty());
+    }
+
+    #[test]
+    fn test_ca_run_unbounded_background() {
+        // Rule 1 turns the OFF background ON, and back again.
+        let gens = Ca::run_unbounded(vec![0], 1, 3);
+        assert_eq!(
+            vec![
+                vec![0, 0, 0, 0, 0],
+                vec![1, 1, 1, 1, 1],
+                vec![0, 0, 0, 0, 0]
+            ],
+            gens
+        );
+    }
+
+    #[test]
+    fn test_ca_eq_and_hash() {
+        let a = Ca::new(vec![0, 1, 1, 0], 110);
+        let mut b = Ca::new(vec![0, 1, 1, 0], 110);
+        assert!(a == b);
+        assert!(a != Ca::new(vec![0, 1, 1, 0], 30));
+        assert!(a != Ca::new(vec![0, 1, 0, 0], 110));
+        assert!(a != Ca::with_boundary(vec![0, 1, 1, 0], 110, BoundaryCondition::Zero));
+
+        let mut seen = HashSet::new();
+        seen.insert(a);
+        assert!(seen.contains(&b));
+        b.step();
+        assert!(!seen.contains(&b));
+    }
+
+    #[test]
+    fn test_ca_new_on_ring() {
+        let mut ca = Ca::new_on_ring(vec![0, 1, 0], 90, 9).unwrap();
+        assert_eq!(9, ca.width());
+        assert_eq!(
+            vec![
+                vec![0, 0, 0, 0, 1, 0, 0, 0, 0],
+                vec![0, 0, 0, 1, 0, 1, 0, 0, 0],
+                vec![0, 0, 1, 0, 0, 0, 1, 0, 0],
+                vec![0, 1, 0, 1, 0, 1, 0, 1, 0],
+                vec![1, 0, 0, 0, 0, 0, 0, 0, 1],
+                // The edges meet on the other side of the ring.
+                vec![1, 1, 0, 0, 0, 0, 0, 1, 1],
+            ],
+            ca.run(6)
+        );
+    }
+
+    #[test]
+    fn test_ca_new_on_ring_invalid() {
+        assert_eq!(
+            Err(CaError::SeedTooShort {
+                width: 2,
+                required: 3
+            }),
+            Ca::new_on_ring(vec![0, 1, 0], 90, 2).map(|ca| ca.state())
+        );
+        assert_eq!(
+            vec![1, 1],
+            Ca::new_on_ring(vec![1, 1], 90, 2).unwrap().state()
+        );
+    }
+
+    #[test]
+    fn test_ca_neighborhood_at() {
+        let ca = Ca::new(vec![1, 0, 1, 1, 0], 30);
+        assert_eq!(Ok([1, 0, 1]), ca.neighborhood_at(1));
+        assert_eq!(Ok([0, 1, 1]), ca.neighborhood_at(2));
+        // The edges wrap around by default.
+        assert_eq!(Ok([0, 1, 0]), ca.neighborhood_at(0));
+        assert_eq!(Ok([1, 0, 1]), ca.neighborhood_at(4));
+        assert_eq!(
+            Err(CaError::OutOfBounds { index: 5, width: 5 }),
+            ca.neighborhood_at(5)
+        );
+
+        let ca = Ca::with_boundary(vec![1, 0, 1, 1, 0], 30, BoundaryCondition::One);
+        assert_eq!(Ok([1, 1, 0]), ca.neighborhood_at(0));
+        assert_eq!(Ok([1, 0, 1]), ca.neighborhood_at(4));
+
+        let ca = Ca::with_background(vec![1, 0, 1], 30, &[0, 1]).unwrap();
+        assert_eq!(Ok([1, 1, 0]), ca.neighborhood_at(0));
+        assert_eq!(Ok([0, 1, 1]), ca.neighborhood_at(2));
+    }
+
+    #[test]
+    fn test_ca_neighborhood_at_matches_step() {
+        for boundary in [BoundaryCondition::Reflect, BoundaryCondition::Twist(2)] {
+            let mut ca = Ca::with_boundary(vec![1, 1, 0, 1, 0, 0, 1], 110, boundary);
+            let table = RuleTable::new(110);
+            let expected: Vec<u8> = (0..ca.width())
+                .map(|i| {
+                    let [l, c, r] = ca.neighborhood_at(i).unwrap();
+                    u8::from(table.get(l.into(), c.into(), r.into()))
+                })
+                .collect();
+            ca.step();
+            assert_eq!(expected, ca.state());
+        }
+    }
+
+    #[test]
+    fn test_ca_reset() {
+        let mut ca = Ca::new(vec![0, 1, 1, 0, 1, 0, 0, 1], 110);
+        let first = ca.run(10);
+        ca.reset();
+        assert_eq!(first, ca.run(10));
+
+        let mut ca = Ca::with_background(
+            vec![1, 0, 0],
+            110,
+            &[0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1, 1, 1],
+        )
+        .unwrap();
+        let first = ca.run(12);
+        ca.reset();
+        assert_eq!(first, ca.run(12));
+    }
+
+    #[test]
+    fn test_ca_reset_after_reseed() {
+        let mut ca = Ca::new(vec![0, 0, 1, 0, 0], 90);
+        ca.reseed(vec![1, 0, 0, 0, 0]).unwrap();
+        ca.run(3);
+        ca.reset();
+        assert_eq!(vec![1, 0, 0, 0, 0], ca.state());
+    }
+
+    #[test]
+    fn test_ca_reseed_background() {
+        // The ether of rule 110.
+        let ether = [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1, 1, 1];
+        let mut ca = Ca::with_background(vec![0, 1, 0], 110, &ether).unwrap();
+        ca.run(5);
+        ca.reseed(vec![1, 1, 0]).unwrap();
+        let mut fresh = Ca::with_background(vec![1, 1, 0], 110, &ether).unwrap();
+        assert!(ca == fresh);
+        assert_eq!(fresh.run(12), ca.run(12));
+
+        let mut ca = Ca::with_background(vec![0, 1, 0], 30, &[1, 0, 0]).unwrap();
+        ca.run(2);
+        ca.reseed(vec![0, 1, 0]).unwrap();
+        let mut fresh = Ca::with_background(vec![0, 1, 0], 30, &[1, 0, 0]).unwrap();
+        assert_eq!(fresh.run(4), ca.run(4));
+    }
+
+    #[test]
+    fn test_ca_eq_ignores_seed() {
+        let mut a = Ca::new(vec![1, 1, 1, 1], 0);
+        a.step();
+        assert!(a == Ca::new(vec![0, 0, 0, 0], 0));
+    }
+
+    #[test]
+    fn test_ca_reseed() {
+        let mut ca = Ca::with_boundary(vec![0, 0, 1, 0, 0], 90, BoundaryCondition::Zero);
+        ca.run(7);
+        ca.reseed(vec![1, 0, 0, 0, 0]).unwrap();
+        let fresh = Ca::with_boundary(vec![1, 0, 0, 0, 0], 90, BoundaryCondition::Zero).run(5);
+        assert_eq!(fresh, ca.run(5));
+        assert_eq!(Err(CaError::DimensionMismatch), ca.reseed(vec![1, 0]));
+    }
+
+    #[test]
+    fn test_ca_from_bits() {
+        let seed = vec![0, 1, 1, 0, 1, 0, 0, 1];
+        let bits: Vec<Bit> = seed.iter().map(|&c| Bit::from(c)).collect();
+        let mut a = Ca::from_bits(bits.clone(), 110);
+        let mut b = Ca::new(seed, 110);
+        assert_eq!(&bits[..], a.state_bits());
+        assert_eq!(b.run(10), a.run(10));
+        assert_eq!(b.state_bits(), a.state_bits());
+    }
+
+    #[test]
+    fn test_ca_width_and_rule() {
+        let mut ca = Ca::new(vec![0, 0, 1, 0, 0], 110);
+        assert_eq!(5, ca.width());
+        assert_eq!(110, ca.rule());
+        ca.run(3);
+        assert_eq!(5, ca.width());
+        assert_eq!(110, ca.rule());
+
+        let ca = Ca::with_boundary(vec![1; 12], 0, BoundaryCondition::Zero);
+        assert_eq!(12, ca.width());
+        assert_eq!(0, ca.rule());
+        assert_eq!(255, Ca::new(vec![], 255).rule());
+    }
+
+    #[test]
+    fn test_ca_state_after_steps() {
+        let mut ca = Ca::new(vec![0, 0, 1, 0, 0], 90);
+        assert_eq!(vec![0, 0, 1, 0, 0], ca.state());
+        ca.step();
+        assert_eq!(vec![0, 1, 0, 1, 0], ca.state());
+        ca.step();
+        assert_eq!(vec![1, 0, 0, 0, 1], ca.state());
+    }
+
+    #[test]
+    fn test_ca_with_threshold() {
+        let ca = Ca::with_threshold(vec![0, 1, 128, 255, 3], 90, 128);
+        assert_eq!(vec![0, 0, 1, 1, 0], ca.state());
+        let ca = Ca::with_threshold(vec![0, 1, 128, 255, 3], 90, 1);
+        assert_eq!(Ca::new(vec![0, 1, 128, 255, 3], 90).state(), ca.state());
+    }
+
+    #[test]
+    fn test_iter_matches_run() {
+        let expected = Ca::new(vec![0, 0, 1, 0, 0], 30).run(5);
+        let mut ca = Ca::new(vec![0, 0, 1, 0, 0], 30);
+        let gens: Vec<Vec<u8>> = ca.iter().take(5).collect();
+        assert_eq!(expected, gens);
+    }
+
+    #[test]
+    fn test_indexed_iter() {
+        let mut ca = Ca::new(vec![0, 0, 1, 0, 0], 90);
+        let gens: Vec<(usize, Vec<u8>)> = ca.indexed_iter().take(3).collect();
+        assert_eq!(
+            vec![
+                (0, vec![0, 0, 1, 0, 0]),
+                (1, vec![0, 1, 0, 1, 0]),
+                (2, vec![1, 0, 0, 0, 1])
+            ],
+            gens
+        );
+    }
+
+    #[test]
+    fn test_run_with_progress() {
+        let mut calls = Vec::new();
+        let res = Ca::new(vec![0, 0, 1, 0, 0], 90).run_with_progress(4, |i| calls.push(i));
+        assert_eq!(vec![0, 1, 2, 3], calls);
+        assert_eq!(Ca::new(vec![0, 0, 1, 0, 0], 90).run(4), res);
+    }
+
+    #[test]
+    fn test_run_sampled_evenly() {
+        let full = Ca::new(seed::single_cell_seed(21, 10).unwrap(), 90).run(100);
+        let mut ca = Ca::new(seed::single_cell_seed(21, 10).unwrap(), 90);
+        let sampled = ca.run_sampled_evenly(100, 30);
+        assert_eq!(30, sampled.len());
+        for (i, state) in sampled.iter().enumerate() {
+            assert_eq!(&full[i * 100 / 30], state);
+        }
+        // All steps are run, leaving the automaton after the last one.
+        let mut rest = Ca::new(seed::single_cell_seed(21, 10).unwrap(), 90);
+        rest.run(100);
+        assert_eq!(rest.state(), ca.state());
+
+        let mut ca = Ca::new(vec![0, 1, 0], 90);
+        assert_eq!(
+            Ca::new(vec![0, 1, 0], 90).run(4),
+            ca.run_sampled_evenly(4, 4)
+        );
+        assert!(ca.run_sampled_evenly(4, 0).is_empty());
+    }
+
+    #[test]
+    fn test_run_sampled() {
+        let full = Ca::new(vec![0, 0, 0, 1, 0, 0, 0], 30).run(10);
+        let sampled = Ca::new(vec![0, 0, 0, 1, 0, 0, 0], 30).run_sampled(10, 2);
+        assert_eq!(5, sampled.len());
+        let expected: Vec<Vec<u8>> = full.into_iter().step_by(2).collect();
+        assert_eq!(expected, sampled);
+    }
+
+    #[test]
+    fn test_run_while_population_threshold() {
+        let mut seed = vec![0; 15];
+        seed[7] = 1;
+        let population = |state: &[u8]| state.iter().filter(|&&c| c == 1).count();
+        // Under rule 30 the population goes 1, 3, 3, 6, 4, ...
+        let mut ca = Ca::new(seed, 30);
+        let res = ca.run_while(10, |state| population(state) < 5);
+        assert_eq!(3, res.len());
+        assert_eq!(6, population(&ca.state()));
+    }
+
+    #[test]
+    fn test_run_while_capped_at_max() {
+        let res = Ca::new(vec![0, 1, 0], 204).run_while(4, |_| true);
+        assert_eq!(4, res.len());
+    }
+
+    #[test]
+    fn test_run_until_dead_rule_0() {
+        let (res, died) = Ca::new(vec![0, 1, 1, 0], 0).run_until_dead(10);
+        assert_eq!(Some(1), died);
+        assert_eq!(vec![vec![0, 1, 1, 0], vec![0, 0, 0, 0]], res);
+    }
+
+    #[test]
+    fn test_run_until_dead_survives() {
+        let (res, died) = Ca::new(vec![0, 1, 1, 0], 204).run_until_dead(10);
+        assert_eq!(None, died);
+        assert_eq!(10, res.len());
+    }
+
+    #[test]
+    fn test_mixing_time() {
+        let mut ca = Ca::new(vec![1, 0, 1, 1, 0, 1, 1, 1], 0);
+        assert_eq!(Some(1), ca.mixing_time(0., 5, 20));
+        // The window has to fit in the generations looked at.
+        let mut ca = Ca::new(vec![1, 0, 1, 1, 0, 1, 1, 1], 0);
+        assert_eq!(None, ca.mixing_time(0., 5, 5));
+        // A single cell under rule 90 on a wide ring keeps changing its
+        // population until it wraps around.
+        let mut ca = Ca::new(seed::single_cell_seed(64, 32).unwrap(), 90);
+        assert_eq!(None, ca.mixing_time(0.01, 4, 16));
+        // Rule 204 keeps any population.
+        let mut ca = Ca::new(vec![1, 0, 1, 1], 204);
+        assert_eq!(Some(0), ca.mixing_time(0., 3, 3));
+    }
+
+    #[test]
+    fn test_ca_into_iter() {
+        let expected = Ca::new(vec![0, 0, 1, 0, 0], 30).run(4);
+        let gens: Vec<Vec<u8>> = Ca::new(vec![0, 0, 1, 0, 0], 30)
+            .into_iter()
+            .take(4)
+            .collect();
+        assert_eq!(expected, gens);
+
+        let mut ca = Ca::new(vec![0, 0, 1, 0, 0], 30);
+        let mut gens = Vec::new();
+        for gen in &mut ca {
+            if gens.len() == 4 {
+                break;
+            }
+            gens.push(gen);
+        }
+        assert_eq!(expected, gens);
+
+        let mut gens = Vec::new();
+        for gen in Ca::new(vec![0, 0, 1, 0, 0], 30) {
+            gens.push(gen);
+            if gens.len() == 4 {
+                break;
+            }
+        }
+        assert_eq!(expected, gens);
+    }
+
+    #[test]
+    fn test_run_into_matches_run() {
+        let expected = Ca::new(vec![0, 0, 1, 0, 0], 30).run(4);
+        let mut buf = Vec::new();
+        Ca::new(vec![0, 0, 1, 0, 0], 30).run_into(4, &mut buf);
+        assert_eq!(expected, buf);
+    }
+
+    #[test]
+    fn test_run_into_reuses_buffer() {
+        let mut buf = vec![vec![1; 10]; 8];
+        Ca::new(vec![0, 0, 1, 0, 0], 90).run_into(2, &mut buf);
+        assert_eq!(vec![vec![0, 0, 1, 0, 0], vec![0, 1, 0, 1, 0]], buf);
+        Ca::new(vec![1, 1, 0], 204).run_into(3, &mut buf);
+        assert_eq!(vec![vec![1, 1, 0]; 3], buf);
+    }
+
+    #[test]
+    fn test_ca_step_boundaries() {
+        let step = |boundary| {
+            let mut ca = Ca::with_boundary(vec![1, 0, 0, 0], 90, boundary);
+            ca.step();
+            ca.state.iter().map(|v| (*v).into()).collect::<Vec<u8>>()
+        };
+        assert_eq!(vec![0, 1, 0, 1], step(BoundaryCondition::Wrap));
+        assert_eq!(vec![0, 1, 0, 0], step(BoundaryCondition::Zero));
+        assert_eq!(vec![1, 1, 0, 1], step(BoundaryCondition::One));
+        assert_eq!(vec![1, 1, 0, 0], step(BoundaryCondition::Reflect));
+        assert_eq!(vec![0, 1, 0, 1], step(BoundaryCondition::Twist(0)));
+        assert_eq!(vec![0, 1, 0, 1], step(BoundaryCondition::Twist(4)));
+    }
+
+    #[test]
+    fn test_boundary_twist_neighbours() {
+        let state: Vec<Bit> = [1, 0, 0, 1, 0].iter().map(|&c| Bit::from(c)).collect();
+        let twist = BoundaryCondition::Twist(2);
+        // Right of the last cell is the cell at index 2 instead of 0, and left
+        // of the first cell the cell at index 2 instead of 4.
+        assert_eq!(Bit::Zero, twist.cell(&state, 5));
+        assert_eq!(Bit::One, twist.cell(&state, 6));
+        assert_eq!(Bit::Zero, twist.cell(&state, -1));
+        assert_eq!(Bit::One, twist.cell(&state, -3));
+        assert_eq!(Bit::One, BoundaryCondition::Twist(1).cell(&state, -1));
+        assert_eq!(Bit::One, twist.cell(&state, 3));
+    }
+
+    #[test]
+    fn test_ca_twist_grows_offset() {
+        // Rule 204 keeps the state, so only the neighbours change.
+        let mut ca = Ca::with_boundary(vec![0, 1, 0, 0, 0], 204, BoundaryCondition::Twist(1));
+        // Right of the last cell is the cell at index 0, then 1, then 2.
+        assert_eq!(Ok([0, 0, 0]), ca.neighborhood_at(4));
+        ca.step();
+        assert_eq!(Ok([0, 0, 1]), ca.neighborhood_at(4));
+        ca.step();
+        assert_eq!(Ok([0, 0, 0]), ca.neighborhood_at(4));
+        // Left of the first cell is the cell at index 4 - 2 = 2.
+        assert_eq!(Ok([0, 0, 1]), ca.neighborhood_at(0));
+        ca.reset();
+        assert_eq!(Ok([0, 0, 0]), ca.neighborhood_at(4));
+    }
+
+    #[test]
+    fn test_ca_twist_steps() {
+        let seed = vec![1, 0, 0, 1, 1, 0, 1];
+        let mut ca = Ca::with_boundary(seed.clone(), 30, BoundaryCondition::Twist(3));
+        let mut expected = seed;
+        for generation in 0..10 {
+            assert_eq!(expected, ca.state());
+            let twist = BoundaryCondition::Twist(3 * generation % 7);
+            expected = apply_rule(&expected, 30, twist);
+            ca.step();
+        }
+    }
+
+    #[test]
+    fn test_ca_twist_eq_phase() {
+        let mut a = Ca::with_boundary(vec![0; 4], 0, BoundaryCondition::Twist(1));
+        let b = Ca::with_boundary(vec![0; 4], 0, BoundaryCondition::Twist(1));
+        a.step();
+        assert!(a != b);
+        for _ in 0..3 {
+            a.step();
+        }
+        assert!(a == b);
     }
 
     #[test]
-    fn test_ca_step() {
+    fn test_boundary_twist_parse() {
+        assert_eq!(Ok(BoundaryCondition::Twist(3)), "twist:3".parse());
+        assert_eq!("twist:3", BoundaryCondition::Twist(3).to_string());
+        assert!("twist:".parse::<BoundaryCondition>().is_err());
+        assert!("twist:-1".parse::<BoundaryCondition>().is_err());
+    }
+
+    #[test]
+    fn test_ca_with_background() {
+        // Rule 170 shifts every cell one step to the left, pulling in the
+        // background from the right.
+        let mut ca = Ca::with_background(vec![0, 0, 0, 0], 170, &[1, 0]).unwrap();
+        assert_eq!(
+            vec![
+                vec![0, 0, 0, 0],
+                vec![0, 0, 0, 1],
+                vec![0, 0, 1, 0],
+                vec![0, 1, 0, 1]
+            ],
+            ca.run(4)
+        );
+        assert!(Ca::with_background(vec![0, 0], 170, &[]).is_err());
+    }
+
+    #[test]
+    fn test_ca_with_background_ether_is_stable() {
+        // A seed matching the background evolves exactly like the background
+        // itself on a ring.
+        let ether = [1, 1, 0];
+        let seed = seed::tile_seed(&ether, 12).unwrap();
+        let mut ca = Ca::with_background(seed.clone(), 90, &ether).unwrap();
+        let mut ring = Ca::new(seed, 90);
+        assert_eq!(ring.run(6), ca.run(6));
+    }
+
+    #[test]
+    fn test_rules_fixing_all_zero() {
+        // Exactly the rules mapping 000 to 0 leave the empty state unchanged.
+        let expected: Vec<u8> = (0..=255).filter(|rule| rule % 2 == 0).collect();
+        assert_eq!(expected, rules_fixing(&[0; 5]));
+    }
+
+    #[test]
+    fn test_rules_fixing_pattern() {
+        let rules = rules_fixing(&[0, 0, 1, 0, 0]);
+        // Rule 204 is the identity and rule 90 always changes a lone cell.
+        assert!(rules.contains(&204));
+        assert!(!rules.contains(&90));
+    }
+
+    #[test]
+    fn test_stream_ascii() {
+        let mut out = Vec::new();
+        Ca::new(vec![0, 0, 1, 0, 0], 90)
+            .stream_ascii(3, &mut out)
+            .unwrap();
+        let expected = TermImage::new(Ca::new(vec![0, 0, 1, 0, 0], 90).run(3)).draw_ascii();
+        assert_eq!(expected.as_bytes(), &out[..]);
+    }
+
+    #[test]
+    fn test_stream_ascii_write_error() {
+        struct Broken;
+
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
         let mut ca = Ca::new(vec![0, 0, 1, 0, 0], 90);
-        ca.step();
-        let state: Vec<u8> = ca.state.iter().map(|v| (*v).into()).collect();
-        assert_eq!(vec![0, 1, 0, 1, 0], state);
+        assert!(ca.stream_ascii(3, &mut Broken).is_err());
+    }
+
+    #[test]
+    fn test_stream_jsonl() {
+        let mut out = Vec::new();
+        Ca::new(vec![0, 0, 1, 0, 0], 90)
+            .stream_jsonl(2, &mut out)
+            .unwrap();
+        assert_eq!(
+            "[0,0,1,0,0]\n[0,1,0,1,0]\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_term_color_from_str() {
+        let names = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white", "reset",
+        ];
+        let colors = [
+            TermColor::Black,
+            TermColor::Red,
+            TermColor::Green,
+            TermColor::Yellow,
+            TermColor::Blue,
+            TermColor::Magenta,
+            TermColor::Cyan,
+            TermColor::White,
+            TermColor::Reset,
+        ];
+        for (name, color) in names.iter().zip(colors.iter()) {
+            assert_eq!(color.to_fg(), name.parse::<TermColor>().unwrap().to_fg());
+        }
+    }
+
+    #[test]
+    fn test_term_color_from_str_invalid() {
+        assert!("purple".parse::<TermColor>().is_err());
+        assert!("Red".parse::<TermColor>().is_err());
+    }
+
+    #[test]
+    fn test_term_color_from_str_hex() {
+        let color: TermColor = "#ff8000".parse().unwrap();
+        assert_eq!("\x1b[38;2;255;128;0m", color.to_fg());
+        assert_eq!("\x1b[48;2;255;128;0m", color.to_bg());
+        assert!("#ff80".parse::<TermColor>().is_err());
+        assert!("#gg8000".parse::<TermColor>().is_err());
+        assert!("#ff800é".parse::<TermColor>().is_err());
     }
 
     #[test]
+    #[allow(clippy::vec_init_then_push)]
     fn test_draw_braille_symbol() {
         let mut data = Vec::new();
         data.push(vec![1, 0]);
@@ -349,6 +3899,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::vec_init_then_push)]
     fn test_draw_half_block_symbol() {
         let mut data = Vec::new();
         data.push(vec![1]);
@@ -365,10 +3916,545 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_draw_plain() {
+        let mut data = vec![vec![1, 1, 0, 0], vec![1, 0, 1, 0], vec![1, 1, 1, 1]];
+        data.push(vec![0, 0, 0, 1]);
+        let image = TermImage::new(data);
+        assert_eq!(image.draw_ascii(), image.draw_plain(Renderer::Ascii));
+        assert_eq!("█▀▄ \n▀▀▀█\n", image.draw_plain(Renderer::Unicode));
+        assert_eq!("⠯⢦\n", image.draw_plain(Renderer::Braille));
+        for renderer in [Renderer::Ascii, Renderer::Unicode, Renderer::Braille] {
+            assert!(!image.draw_plain(renderer).contains('\x1b'));
+        }
+        // The colored braille output wraps the same symbols.
+        assert!(image
+            .draw_braille(TermColor::Green, TermColor::Black)
+            .contains(&image.draw_plain(Renderer::Braille)));
+    }
+
+    #[test]
+    fn test_draw_unicode_staggered() {
+        let image = TermImage::new(vec![vec![1, 0]; 6]);
+        let (fg, bg) = (TermColor::White, TermColor::Black);
+        let plain = image.draw_unicode(fg, bg);
+        assert_eq!(plain, image.draw_unicode_staggered(fg, bg, false));
+
+        let staggered = image.draw_unicode_staggered(fg, bg, true);
+        let space = format!("{} {}", bg.to_bg(), TermColor::Reset);
+        let plain_rows: Vec<&str> = plain.lines().collect();
+        let rows: Vec<&str> = staggered.lines().collect();
+        assert_eq!(3, rows.len());
+        assert_eq!(plain_rows[0], rows[0]);
+        assert_eq!(format!("{}{}", space, plain_rows[1]), rows[1]);
+        assert_eq!(plain_rows[2], rows[2]);
+    }
+
+    #[test]
+    fn test_draw_unicode_glyphs() {
+        let image = TermImage::new(vec![vec![1, 1, 0, 0], vec![1, 0, 1, 0], vec![1, 1, 1, 1]]);
+        assert_eq!(
+            format!("{}█▀▄ {}\n", TermColor::Green.to_fg(), TermColor::Reset),
+            image.draw_unicode_glyphs(TermColor::Green)
+        );
+        assert_eq!(
+            "",
+            TermImage::new(vec![vec![1]]).draw_unicode_glyphs(TermColor::Green)
+        );
+    }
+
+    #[test]
+    fn test_draw_unicode_palette() {
+        let image = TermImage::new(vec![vec![2, 0], vec![1, 7]]);
+        let palette = [TermColor::Black, TermColor::Red, TermColor::Blue];
+        assert_eq!(
+            format!(
+                "{}{}▄{}{}{}▄{}\n",
+                TermColor::Blue.to_bg(),
+                TermColor::Red.to_fg(),
+                TermColor::Reset,
+                TermColor::Black.to_bg(),
+                TermColor::Blue.to_fg(),
+                TermColor::Reset
+            ),
+            image.draw_unicode_palette(&palette)
+        );
+        assert_eq!("", image.draw_unicode_palette(&[]));
+    }
+
+    #[test]
+    fn test_render_functions_match_methods() {
+        let data = Ca::new(vec![0, 0, 0, 1, 0, 0, 1, 0, 0], 30).run(9);
+        let image = TermImage::new(data.clone());
+        assert_eq!(image.draw_ascii(), render_ascii(&data));
+        assert_eq!(
+            image.draw_unicode(TermColor::White, TermColor::Black),
+            render_unicode(&data, TermColor::White, TermColor::Black)
+        );
+        assert_eq!(
+            image.draw_braille(TermColor::White, TermColor::Black),
+            render_braille(&data, TermColor::White, TermColor::Black)
+        );
+        assert_eq!("", render_braille(&[], TermColor::White, TermColor::Black));
+    }
+
+    #[test]
+    fn test_draw_braille_mapped() {
+        // Only the top right and second row left cells are ON.
+        let image = TermImage::new(vec![vec![0, 1], vec![1, 0], vec![0, 0], vec![0, 0]]);
+        let wrap = |c: char| {
+            format!(
+                "{}{}{}\n{}",
+                TermColor::White.to_fg(),
+                TermColor::Black.to_bg(),
+                c,
+                TermColor::Reset
+            )
+        };
+        assert_eq!(
+            wrap('\u{280a}'),
+            image.draw_braille_mapped(TermColor::White, TermColor::Black, &BRAILLE_STANDARD)
+        );
+        assert_eq!(
+            image.draw_braille(TermColor::White, TermColor::Black),
+            image.draw_braille_mapped(TermColor::White, TermColor::Black, &BRAILLE_STANDARD)
+        );
+        assert_eq!(
+            wrap('\u{2806}'),
+            image.draw_braille_mapped(TermColor::White, TermColor::Black, &BRAILLE_ROW_MAJOR)
+        );
+    }
+
+    #[test]
+    fn test_line_ending_crlf() {
+        let mut image = TermImage::new(vec![vec![1, 0], vec![0, 1]]);
+        assert_eq!("#.\n.#\n", image.draw_ascii());
+        image.set_line_ending(LineEnding::CrLf);
+        assert_eq!("#.\r\n.#\r\n", image.draw_ascii());
+        assert_eq!("0 | #.\r\n1 | .#\r\n", image.draw_ascii_numbered());
+        assert!(image
+            .draw_unicode(TermColor::White, TermColor::Black)
+            .ends_with("\r\n"));
+        assert_eq!(
+            1,
+            image
+                .draw_unicode_glyphs(TermColor::White)
+                .matches("\r\n")
+                .count()
+        );
+        // Derived images keep the line ending.
+        assert_eq!("#\r\n", image.crop(0..1, 0..1).draw_ascii());
+    }
+
+    #[test]
+    fn test_draw_layered() {
+        let mut image = TermImage::new(vec![vec![1, 0], vec![1, 0]]);
+        image
+            .add_layer(Layer {
+                data: vec![vec![0, 0], vec![1, 0]],
+                color: TermColor::Red,
+            })
+            .unwrap();
+        assert_eq!(
+            format!(
+                "{}{}▄{}{}{}▄{}\n",
+                TermColor::White.to_bg(),
+                TermColor::Red.to_fg(),
+                TermColor::Reset,
+                TermColor::Black.to_bg(),
+                TermColor::Black.to_fg(),
+                TermColor::Reset
+            ),
+            image.draw_layered(TermColor::White, TermColor::Black)
+        );
+
+        // The last layer is on top.
+        image
+            .add_layer(Layer {
+                data: vec![vec![0, 0], vec![1, 1]],
+                color: TermColor::Blue,
+            })
+            .unwrap();
+        let layered = image.draw_layered(TermColor::White, TermColor::Black);
+        assert_eq!(2, layered.matches(&TermColor::Blue.to_fg()).count());
+        assert!(!layered.contains(&TermColor::Red.to_fg()));
+    }
+
+    #[test]
+    fn test_layers_follow_transforms() {
+        let mut image = TermImage::new(vec![vec![0, 0, 0], vec![0, 0, 0]]);
+        image
+            .add_layer(Layer {
+                data: vec![vec![1, 0, 0], vec![0, 0, 0]],
+                color: TermColor::Red,
+            })
+            .unwrap();
+        assert!(image
+            .add_layer(Layer {
+                data: vec![vec![1, 0]],
+                color: TermColor::Red,
+            })
+            .is_err());
+
+        image.flip_horizontal();
+        assert_eq!(vec![vec![0, 0, 1], vec![0, 0, 0]], image.layers[0].data);
+        let transposed = image.transpose();
+        assert_eq!(
+            vec![vec![0, 0], vec![0, 0], vec![1, 0]],
+            transposed.layers[0].data
+        );
+        let cropped = image.crop(0..1, 1..3);
+        assert_eq!(vec![vec![0, 1]], cropped.layers[0].data);
+        let aligned = image.align(3, VerticalAlign::Bottom);
+        assert_eq!(
+            vec![vec![0; 3], vec![0; 3], vec![0, 0, 1]],
+            aligned.layers[0].data
+        );
+    }
+
+    #[test]
+    fn test_draw_diff() {
+        let a = TermImage::new(vec![vec![1, 0], vec![0, 1]]);
+        let b = TermImage::new(vec![vec![1, 1], vec![0, 0]]);
+        assert_eq!(
+            Ok(format!(
+                "{}{}▄{}{}{}▄{}\n",
+                TermColor::Black.to_bg(),
+                TermColor::Black.to_fg(),
+                TermColor::Reset,
+                TermColor::Red.to_bg(),
+                TermColor::Red.to_fg(),
+                TermColor::Reset
+            )),
+            TermImage::draw_diff(&a, &b, TermColor::Black, TermColor::Red)
+        );
+    }
+
+    #[test]
+    fn test_draw_diff_mismatched() {
+        let a = TermImage::new(vec![vec![1, 0], vec![0, 1]]);
+        let b = TermImage::new(vec![vec![1, 0, 1], vec![0, 1, 0]]);
+        assert!(TermImage::draw_diff(&a, &b, TermColor::Black, TermColor::Red).is_err());
+        let c = TermImage::new(vec![vec![1, 0]]);
+        assert!(TermImage::draw_diff(&a, &c, TermColor::Black, TermColor::Red).is_err());
+    }
+
+    #[test]
+    fn test_draw_empty_and_small_images() {
+        let empty = TermImage::new(vec![]);
+        assert_eq!("", empty.draw_ascii());
+        assert_eq!("", empty.draw_unicode(TermColor::White, TermColor::Black));
+        assert_eq!("", empty.draw_braille(TermColor::White, TermColor::Black));
+
+        let one_row = TermImage::new(vec![vec![1, 0]]);
+        assert_eq!("#.\n", one_row.draw_ascii());
+        assert_eq!("", one_row.draw_unicode(TermColor::White, TermColor::Black));
+        assert_eq!("", one_row.draw_braille(TermColor::White, TermColor::Black));
+
+        let two_rows = TermImage::new(vec![vec![1, 0], vec![0, 1]]);
+        assert_eq!("#.\n.#\n", two_rows.draw_ascii());
+        assert_eq!(
+            1,
+            two_rows
+                .draw_unicode(TermColor::White, TermColor::Black)
+                .lines()
+                .count()
+        );
+        assert_eq!(
+            "",
+            two_rows.draw_braille(TermColor::White, TermColor::Black)
+        );
+
+        let empty_rows = TermImage::new(vec![vec![]; 4]);
+        assert_eq!("\n\n\n\n", empty_rows.draw_ascii());
+        assert_eq!(
+            "\n\n",
+            empty_rows.draw_unicode(TermColor::White, TermColor::Black)
+        );
+        assert_eq!(
+            format!(
+                "{}{}\n{}",
+                TermColor::White.to_fg(),
+                TermColor::Black.to_bg(),
+                TermColor::Reset
+            ),
+            empty_rows.draw_braille(TermColor::White, TermColor::Black)
+        );
+    }
+
+    #[test]
+    fn test_term_image_ragged_rows() {
+        let data = vec![vec![1, 0, 1], vec![0, 1, 1, 1, 1]];
+        let image = TermImage::new(data.clone());
+        assert_eq!("#.#..\n.####\n", image.draw_ascii());
+        assert_eq!("▀▄█▄▄\n", image.draw_plain(Renderer::Unicode));
+        assert_eq!(
+            image.draw_unicode(TermColor::White, TermColor::Black),
+            render_unicode(&data, TermColor::White, TermColor::Black)
+        );
+        assert_eq!("#.#..\n.####\n", render_ascii(&data));
+        assert_eq!(
+            vec![vec![1, 0], vec![0, 1], vec![1, 1], vec![0, 1], vec![0, 1]],
+            image.transpose().data
+        );
+        assert_eq!(5, image.to_char_grid('#', '.')[0].len());
+
+        let mut tall = data.clone();
+        tall.extend(vec![vec![1], vec![]]);
+        let image = TermImage::new(tall.clone());
+        assert_eq!(
+            image.draw_braille(TermColor::White, TermColor::Black),
+            render_braille(&tall, TermColor::White, TermColor::Black)
+        );
+        assert_eq!("⠕⠓\n", image.draw_plain(Renderer::Braille));
+    }
+
+    #[test]
+    fn test_draw_textured() {
+        let glyphs = ['0', '1', '2', '3', '4', '5', '6', '7'];
+        let image = TermImage::new(vec![vec![1, 0, 1, 1, 0, 0], vec![0; 6]]);
+        assert_eq!("253641\n000000\n", image.draw_textured(glyphs));
+    }
+
+    #[test]
+    fn test_to_xbm() {
+        let image = TermImage::new(vec![
+            vec![1, 1, 0, 0, 0, 0, 0, 1, 1, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+        ]);
+        assert_eq!(
+            "#define ca_width 10\n\
+             #define ca_height 2\n\
+             static char ca_bits[] = {\n   \
+             0x83, 0x01, 0x00, 0x02\n};\n",
+            image.to_xbm("ca")
+        );
+    }
+
     #[test]
     fn test_draw_ascii() {
         let data = vec![vec![0, 1, 0, 1, 0]];
         let image = TermImage::new(data);
         assert_eq!(".#.#.\n", image.draw_ascii());
     }
+
+    #[test]
+    fn test_term_image_with_threshold() {
+        let image = TermImage::with_threshold(vec![vec![0, 1, 254, 255]], 255);
+        assert_eq!("...#\n", image.draw_ascii());
+        let image = TermImage::with_threshold(vec![vec![0, 1, 254, 255]], 1);
+        assert_eq!(".###\n", image.draw_ascii());
+    }
+
+    #[test]
+    fn test_to_char_grid() {
+        let image = TermImage::new(vec![vec![0, 1, 0], vec![1, 1, 0]]);
+        let grid = image.to_char_grid('X', ' ');
+        assert_eq!(2, grid.len());
+        assert!(grid.iter().all(|row| row.len() == 3));
+        assert_eq!(vec![vec![' ', 'X', ' '], vec!['X', 'X', ' ']], grid);
+    }
+
+    #[test]
+    fn test_to_png_transparent_off() {
+        let image = TermImage::new(vec![vec![0; 3]; 2]);
+        let png = image.to_png([0, 0, 0, 255], [255, 255, 255, 0]);
+        let (width, height, pixels) = export::tests::decode_png(&png);
+        assert_eq!((3, 2), (width, height));
+        assert!(pixels.chunks(4).all(|px| px[3] == 0));
+    }
+
+    #[test]
+    fn test_to_png_empty() {
+        let off = [4, 5, 6, 0];
+        for data in [vec![], vec![vec![]; 3]] {
+            let png = TermImage::new(data).to_png([1, 2, 3, 255], off);
+            assert_eq!((1, 1, off.to_vec()), export::tests::decode_png(&png));
+        }
+        let png = TermImage::new(vec![]).to_png_downscaled([1, 2, 3, 255], off, 4);
+        assert_eq!((1, 1, off.to_vec()), export::tests::decode_png(&png));
+    }
+
+    #[test]
+    fn test_to_png_colors() {
+        let image = TermImage::new(vec![vec![1, 0]]);
+        let png = image.to_png([1, 2, 3, 255], [4, 5, 6, 0]);
+        let (_, _, pixels) = export::tests::decode_png(&png);
+        assert_eq!(vec![1, 2, 3, 255, 4, 5, 6, 0], pixels);
+    }
+
+    #[test]
+    fn test_to_png_downscaled_full_block() {
+        let image = TermImage::new(vec![vec![1; 4]; 4]);
+        let png = image.to_png_downscaled([10, 20, 30, 255], [0, 0, 0, 0], 4);
+        assert_eq!(
+            (1, 1, vec![10, 20, 30, 255]),
+            export::tests::decode_png(&png)
+        );
+    }
+
+    #[test]
+    fn test_to_png_downscaled_averages() {
+        // Two 2x2 blocks, one with a single ON cell and one half ON, plus a
+        // partial 1x2 block at the right edge.
+        let image = TermImage::new(vec![vec![1, 0, 1, 1, 0], vec![0, 0, 0, 0, 1]]);
+        let png = image.to_png_downscaled([200, 200, 200, 200], [0, 0, 0, 0], 2);
+        let (width, height, pixels) = export::tests::decode_png(&png);
+        assert_eq!((3, 1), (width, height));
+        let reds: Vec<u8> = pixels.chunks(4).map(|px| px[0]).collect();
+        assert_eq!(vec![50, 100, 100], reds);
+    }
+
+    #[test]
+    fn test_to_svg_transparent_off() {
+        let image = TermImage::new(vec![vec![0, 1], vec![0, 0]]);
+        let svg = image.to_svg("#000000", None);
+        assert_eq!(1, svg.matches("<rect").count());
+        assert!(svg.contains("<rect x=\"1\" y=\"0\""));
+
+        let svg = image.to_svg("#000000", Some("#ffffff"));
+        assert_eq!(2, svg.matches("<rect").count());
+    }
+
+    #[test]
+    fn test_comment_embedded() {
+        let mut image = TermImage::new(vec![vec![0, 1]]);
+        image.set_comment("rule=90 width=2");
+        assert!(image
+            .to_svg("#000000", None)
+            .contains("<!-- rule=90 width=2 -->"));
+        let png = image.to_png([0, 0, 0, 255], [255, 255, 255, 255]);
+        let needle = b"tEXtComment\0rule=90 width=2";
+        assert!(png.windows(needle.len()).any(|w| w == needle));
+        // The comment is kept when deriving new images.
+        assert!(image.crop(0..1, 0..1).comment.is_some());
+    }
+
+    #[test]
+    fn test_svg_comment_dashes() {
+        assert_eq!("a- - -b", xml_comment_text("a---b"));
+        assert_eq!("- - ", xml_comment_text("--"));
+        assert_eq!("x- ", xml_comment_text("x-"));
+
+        let mut image = TermImage::new(vec![vec![0, 1]]);
+        image.set_comment("a---b -");
+        let svg = image.to_svg("#000000", None);
+        let start = svg.find("<!--").unwrap() + 4;
+        let end = svg.find("-->").unwrap();
+        let comment = &svg[start..end];
+        assert!(!comment.contains("--"));
+        assert!(!comment.ends_with('-'));
+        assert_eq!(1, svg.matches("-->").count());
+    }
+
+    #[test]
+    fn test_draw_ascii_scaled() {
+        let image = TermImage::new(vec![vec![0, 1, 0], vec![1, 1, 0]]);
+        assert_eq!("..##..\n####..\n", image.draw_ascii_scaled(2));
+        assert_eq!(image.draw_ascii(), image.draw_ascii_scaled(1));
+    }
+
+    #[test]
+    fn test_draw_ascii_numbered() {
+        let image = TermImage::new(vec![vec![0, 1, 0], vec![1, 0, 1], vec![0, 0, 0]]);
+        assert_eq!("0 | .#.\n1 | #.#\n2 | ...\n", image.draw_ascii_numbered());
+    }
+
+    #[test]
+    fn test_draw_ascii_numbered_gutter_width() {
+        let image = TermImage::new(vec![vec![1]; 11]);
+        let numbered = image.draw_ascii_numbered();
+        let lines: Vec<&str> = numbered.lines().collect();
+        assert_eq!("00 | #", lines[0]);
+        assert_eq!("10 | #", lines[10]);
+    }
+
+    #[test]
+    fn test_flip_horizontal() {
+        let data = vec![vec![1, 1, 0, 0, 0], vec![0, 1, 0, 0, 1]];
+        let mut image = TermImage::new(data.clone());
+        image.flip_horizontal();
+        assert_eq!(vec![vec![0, 0, 0, 1, 1], vec![1, 0, 0, 1, 0]], image.data);
+        image.flip_horizontal();
+        assert_eq!(data, image.data);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let data = vec![vec![1, 1, 0], vec![0, 1, 1]];
+        let image = TermImage::new(data.clone());
+        let transposed = image.transpose();
+        assert_eq!(vec![vec![1, 0], vec![1, 1], vec![0, 1]], transposed.data);
+        assert_eq!(data, transposed.transpose().data);
+        assert!(TermImage::new(vec![]).transpose().data.is_empty());
+    }
+
+    #[test]
+    fn test_mirror_symmetric_rule_evolution() {
+        let seed = vec![1, 1, 0, 1, 0, 0, 0, 0, 1];
+        let mut flipped = TermImage::new(Ca::new(seed.clone(), 90).run(8));
+        flipped.flip_horizontal();
+
+        let mut ca = Ca::new(seed, 90);
+        ca.mirror();
+        assert_eq!(flipped.data, ca.run(8));
+    }
+
+    #[test]
+    fn test_invert() {
+        let data = vec![vec![0, 1, 0, 1, 0]];
+        let mut image = TermImage::new(data.clone());
+        image.invert();
+        assert_eq!("#.#.#\n", image.draw_ascii());
+        image.invert();
+        assert_eq!(data, image.data);
+    }
+
+    #[test]
+    fn test_align_top() {
+        let image = TermImage::new(vec![vec![1, 1], vec![0, 1]]);
+        assert_eq!(
+            vec![vec![1, 1], vec![0, 1], vec![0, 0]],
+            image.align(3, VerticalAlign::Top).data
+        );
+    }
+
+    #[test]
+    fn test_align_center() {
+        let image = TermImage::new(vec![vec![1, 1]; 6]);
+        let aligned = image.align(6, VerticalAlign::Center);
+        assert_eq!(vec![vec![0, 0]; 3], aligned.data[..3].to_vec());
+        assert_eq!(vec![vec![1, 1]; 3], aligned.data[3..].to_vec());
+    }
+
+    #[test]
+    fn test_align_bottom() {
+        let image = TermImage::new(vec![vec![1], vec![0], vec![1]]);
+        assert_eq!(
+            vec![vec![0], vec![0], vec![1]],
+            image.align(3, VerticalAlign::Bottom).data
+        );
+    }
+
+    #[test]
+    fn test_crop_full_range() {
+        let data = vec![vec![0, 1, 0], vec![1, 0, 1]];
+        let image = TermImage::new(data.clone());
+        assert_eq!(data, image.crop(0..2, 0..3).data);
+    }
+
+    #[test]
+    fn test_crop_sub_rectangle() {
+        let data = vec![vec![0, 1, 0, 1], vec![1, 0, 1, 1], vec![1, 1, 0, 0]];
+        let image = TermImage::new(data);
+        assert_eq!(vec![vec![0, 1], vec![1, 0]], image.crop(1..3, 1..3).data);
+    }
+
+    #[test]
+    fn test_crop_out_of_bounds_clamps() {
+        let data = vec![vec![0, 1, 0], vec![1, 0, 1]];
+        let image = TermImage::new(data);
+        assert_eq!(vec![vec![0, 1]], image.crop(1..10, 1..10).data);
+        assert!(image.crop(5..10, 0..3).data.is_empty());
+    }
 }
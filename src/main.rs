@@ -1,24 +1,78 @@
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
 use clap::{crate_version, App, Arg};
-use rand::Rng;
-use terminal_size::{terminal_size, Height, Width};
 
-use eca1d::{Ca, TermColor, TermImage};
+use rand::rngs::StdRng;
+use rand::{FromEntropy, SeedableRng};
 
-fn is_binary_or_u8(val: String) -> Result<(), String> {
-    let err = String::from("has to be binary string (ex 0b01010101) or number between 0-255");
-    if val.starts_with("0b") {
-        match u8::from_str_radix(&val[2..], 2) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(err),
-        }
-    } else {
-        match val.parse::<u8>() {
-            Ok(_) => Ok(()),
-            Err(_) => Err(err),
+use eca1d::seed::{
+    centered_seed, clustered_seed, from_pattern, from_pattern_strict, random_seed,
+    single_cell_seed, tile_seed,
+};
+use eca1d::terminal::fit_to_terminal;
+use eca1d::{
+    canonical_rule, parse_rule_name, rule_table_string, BoundaryCondition, Ca, CaError, LineEnding,
+    Renderer, TermColor, TermImage, VerticalAlign,
+};
+
+/// Well-known rules that can be given by name instead of by number.
+const RULE_ALIASES: [(&str, u8); 8] = [
+    ("rule30", 30),
+    ("rule90", 90),
+    ("rule110", 110),
+    ("rule184", 184),
+    ("sierpinski", 90),
+    ("traffic", 184),
+    ("majority", 232),
+    ("identity", 204),
+];
+
+fn parse_rule(val: &str) -> Result<u8, String> {
+    if let Some(bin) = val.strip_prefix("0b") {
+        return u8::from_str_radix(bin, 2)
+            .map_err(|_| String::from("has to be binary string (ex 0b01010101)"));
+    }
+    if let Some(hex) = val.strip_prefix("0x") {
+        return u8::from_str_radix(hex, 16)
+            .map_err(|_| String::from("has to be a hex number between 0x00-0xff (ex 0x6e)"));
+    }
+    if let Ok(rule) = val.parse::<u8>() {
+        return Ok(rule);
+    }
+    if val.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(String::from("has to be a number between 0-255"));
+    }
+    if let Ok(rule) = parse_rule_name(val) {
+        return Ok(rule);
+    }
+    match RULE_ALIASES.iter().find(|(name, _)| *name == val) {
+        Some((_, rule)) => Ok(*rule),
+        None => {
+            let names: Vec<&str> = RULE_ALIASES.iter().map(|(name, _)| *name).collect();
+            Err(format!(
+                "unknown rule name, valid names are: {}",
+                names.join(", ")
+            ))
         }
     }
 }
 
+fn is_rule(val: String) -> Result<(), String> {
+    parse_rule(&val).map(|_| ())
+}
+
+/// Parses a list of rules separated by commas or newlines, returning each
+/// entry along with its parsed rule. Empty entries are skipped.
+fn parse_rule_list(list: &str) -> Vec<(&str, Result<u8, String>)> {
+    list.split([',', '\n'])
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| (entry, parse_rule(entry)))
+        .collect()
+}
+
 fn is_usize(val: String) -> Result<(), String> {
     match val.parse::<usize>() {
         Ok(_) => Ok(()),
@@ -26,6 +80,7 @@ fn is_usize(val: String) -> Result<(), String> {
     }
 }
 
+#[allow(clippy::manual_range_contains)]
 fn is_float_between_0_1(val: String) -> Result<(), String> {
     let num = match val.parse::<f64>() {
         Ok(v) => v,
@@ -38,16 +93,122 @@ fn is_float_between_0_1(val: String) -> Result<(), String> {
     Ok(())
 }
 
+fn is_u64(val: String) -> Result<(), String> {
+    match val.parse::<u64>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(String::from("has to be a number")),
+    }
+}
+
+fn is_non_negative_float(val: String) -> Result<(), String> {
+    match val.parse::<f64>() {
+        Ok(v) if v >= 0. => Ok(()),
+        _ => Err(String::from("has to be a non-negative number")),
+    }
+}
+
+fn is_color(val: String) -> Result<(), String> {
+    val.parse::<TermColor>()
+        .map(|_| ())
+        .map_err(|_| String::from("has to be a color name (ex red) or hex color (ex #ff8000)"))
+}
+
+fn is_boundary(val: String) -> Result<(), String> {
+    val.parse::<BoundaryCondition>()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn is_binary_pattern(val: String) -> Result<(), String> {
+    match from_pattern(&val) {
+        Ok(cells) if !cells.is_empty() => Ok(()),
+        _ => Err(String::from("has to be a non-empty string of 0's and 1's")),
+    }
+}
+
+/// Parses a seed pattern, tolerating whitespace and `#` comments unless
+/// `strict` is set.
+fn parse_pattern(val: &str, strict: bool) -> Result<Vec<u8>, CaError> {
+    let cells = if strict {
+        from_pattern_strict(val)?
+    } else {
+        from_pattern(val)?
+    };
+    if cells.is_empty() {
+        return Err(CaError::EmptyData("seed pattern"));
+    }
+    Ok(cells)
+}
+
+/// Encodes `image` in the format given by the extension of `path`, as PNG,
+/// SVG, XBM or otherwise ASCII text preceded by `header`, if any.
+fn encode_image(path: &Path, image: &TermImage, header: Option<&str>, newline: &str) -> Vec<u8> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => image.to_png([0, 0, 0, 255], [255, 255, 255, 255]),
+        Some("svg") => image.to_svg("#000000", Some("#ffffff")).into_bytes(),
+        Some("xbm") => {
+            // XBM declares C identifiers named after the file.
+            let name: String = path
+                .file_stem()
+                .map_or("eca1d".into(), |stem| stem.to_string_lossy().into_owned())
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            image.to_xbm(&name).into_bytes()
+        }
+        _ => {
+            let mut text = String::new();
+            if let Some(header) = header {
+                text.push_str(&format!("# {}{}", header, newline));
+            }
+            text.push_str(&image.draw_ascii());
+            text.into_bytes()
+        }
+    }
+}
+
+/// Renders `image` for the terminal, with ANSI colors for the unicode and
+/// braille renderers unless `colors` is `None`. `stagger` shifts every other
+/// row of colored unicode output.
+fn render(
+    image: &TermImage,
+    renderer: Renderer,
+    colors: Option<(TermColor, TermColor)>,
+    stagger: bool,
+) -> String {
+    match (renderer, colors) {
+        (Renderer::Braille, Some((fg, bg))) => image.draw_braille(fg, bg),
+        (Renderer::Unicode, Some((fg, bg))) => image.draw_unicode_staggered(fg, bg, stagger),
+        (renderer, _) => image.draw_plain(renderer),
+    }
+}
+
+/// Formats the time taken to simulate `cells` cells and the throughput.
+fn format_stats(elapsed: Duration, cells: usize) -> String {
+    let secs = elapsed.as_secs_f64();
+    let throughput = if secs > 0. {
+        cells as f64 / secs
+    } else {
+        f64::INFINITY
+    };
+    format!(
+        "simulated {} cells in {:.3} ms ({:.0} cells/s)",
+        cells,
+        secs * 1000.,
+        throughput
+    )
+}
+
 fn main() {
     let matches = App::new("Elementary 1D Cellular Automata Explorer")
                             .version(crate_version!())
                             .about("Quickly explore different rules for elementary 1D cellular automata.")
                             .arg(Arg::with_name("rule")
                                 .takes_value(true)
-                                .required(true)
-                                .validator(is_binary_or_u8)
+                                .required_unless("from_stdin")
+                                .validator(is_rule)
                                 .index(1)
-                                .help("The rule to use (0-255)."))
+                                .help("The rule to use (0-255, 0b01010101, 0x6e or a name like sierpinski)."))
                             .arg(Arg::with_name("width")
                                 .short("w")
                                 .long("width")
@@ -60,12 +221,67 @@ fn main() {
                                 .takes_value(true)
                                 .validator(is_usize)
                                 .help("The number of simulation steps (defaults to the height of the terminal window - 1)."))
+                            .arg(Arg::with_name("sim_steps")
+                                .long("sim-steps")
+                                .takes_value(true)
+                                .validator(is_usize)
+                                .help("Simulate <sim_steps> steps and render <iter> rows sampled evenly from them."))
                             .arg(Arg::with_name("random")
                                 .short("r")
                                 .long("random")
                                 .takes_value(true)
                                 .validator(is_float_between_0_1)
                                 .help("Randomly generated seed with density <random>."))
+                            .arg(Arg::with_name("tile")
+                                .short("t")
+                                .long("tile")
+                                .takes_value(true)
+                                .validator(is_binary_pattern)
+                                .conflicts_with("random")
+                                .help("Seed made by repeating the pattern <tile> (ex 0110) across the width."))
+                            .arg(Arg::with_name("seed")
+                                .short("s")
+                                .long("seed")
+                                .takes_value(true)
+                                .validator(is_binary_pattern)
+                                .conflicts_with_all(&["random", "tile", "width"])
+                                .help("Seed given as a pattern of 0's and 1's (ex \"0110 # comment\"), sets the width."))
+                            .arg(Arg::with_name("strict")
+                                .long("strict")
+                                .help("Only accept 0's and 1's in <seed> and <tile>, no whitespace or comments."))
+                            .arg(Arg::with_name("seed_pos")
+                                .long("seed-pos")
+                                .takes_value(true)
+                                .validator(is_usize)
+                                .conflicts_with_all(&["random", "tile", "seed", "clusters"])
+                                .help("The position of the single ON cell in the seed (defaults to the center)."))
+                            .arg(Arg::with_name("centered")
+                                .long("centered")
+                                .conflicts_with_all(&["random", "tile", "seed", "clusters", "seed_pos"])
+                                .help("Use two center cells for even widths to keep the seed symmetric."))
+                            .arg(Arg::with_name("clusters")
+                                .long("clusters")
+                                .takes_value(true)
+                                .validator(is_usize)
+                                .conflicts_with_all(&["random", "tile", "seed"])
+                                .help("Randomly generated seed with <clusters> blobs of ON cells."))
+                            .arg(Arg::with_name("spread")
+                                .long("spread")
+                                .takes_value(true)
+                                .validator(is_non_negative_float)
+                                .requires("clusters")
+                                .help("The spread in cells of each cluster (defaults to 2)."))
+                            .arg(Arg::with_name("rng_seed")
+                                .long("rng-seed")
+                                .takes_value(true)
+                                .validator(is_u64)
+                                .help("Seed for the random number generator to make random seeds reproducible."))
+                            .arg(Arg::with_name("boundary")
+                                .long("boundary")
+                                .takes_value(true)
+                                .validator(is_boundary)
+                                .default_value("wrap")
+                                .help("How to treat the cells beyond the edges (wrap, zero, one, reflect or twist:N)."))
                             .arg(Arg::with_name("braille")
                                 .short("b")
                                 .long("braille")
@@ -75,99 +291,413 @@ fn main() {
                                 .short("u")
                                 .long("unicode")
                                 .help("Draw the image using unicode HALF BLOCK symbols"))
+                            .arg(Arg::with_name("align")
+                                .short("a")
+                                .long("align")
+                                .takes_value(true)
+                                .possible_values(&["top", "center", "bottom"])
+                                .default_value("top")
+                                .help("Where to place the seed row in the image."))
+                            .arg(Arg::with_name("fg")
+                                .long("fg")
+                                .takes_value(true)
+                                .validator(is_color)
+                                .default_value("white")
+                                .help("The color of ON cells in unicode and braille mode."))
+                            .arg(Arg::with_name("bg")
+                                .long("bg")
+                                .takes_value(true)
+                                .validator(is_color)
+                                .default_value("black")
+                                .help("The color of OFF cells in unicode and braille mode."))
+                            .arg(Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .takes_value(true)
+                                .help("Write the image to a file instead, as PNG, SVG or XBM for .png, .svg and .xbm files."))
+                            .arg(Arg::with_name("from_stdin")
+                                .long("from-stdin")
+                                .requires("output_dir")
+                                .conflicts_with_all(&["rule", "summary", "print_rules", "jsonl"])
+                                .help("Read a comma or newline separated list of rules from stdin."))
+                            .arg(Arg::with_name("output_dir")
+                                .long("output-dir")
+                                .takes_value(true)
+                                .conflicts_with_all(&["output", "jsonl"])
+                                .help("Write an image per rule into this directory, named like rule30.png."))
+                            .arg(Arg::with_name("output_format")
+                                .long("output-format")
+                                .takes_value(true)
+                                .possible_values(&["txt", "png", "svg", "xbm"])
+                                .default_value("png")
+                                .help("The format of the images written with --output-dir."))
+                            .arg(Arg::with_name("jsonl")
+                                .long("jsonl")
+                                .conflicts_with_all(&["output", "summary", "braille", "unicode"])
+                                .help("Stream each generation to stdout as a JSON array on its own line."))
+                            .arg(Arg::with_name("header")
+                                .long("header")
+                                .help("Include the parameters used as a comment in the output."))
+                            .arg(Arg::with_name("summary")
+                                .long("summary")
+                                .help("Print a summary of the rule's behaviour from a random seed instead of the image."))
+                            .arg(Arg::with_name("invert")
+                                .long("invert")
+                                .conflicts_with("jsonl")
+                                .help("Swap ON and OFF cells in the image."))
+                            .arg(Arg::with_name("no_color")
+                                .long("no-color")
+                                .conflicts_with_all(&["fg", "bg"])
+                                .help("Don't color the unicode and braille output, drawing only the glyphs."))
+                            .arg(Arg::with_name("stagger")
+                                .long("stagger")
+                                .requires("unicode")
+                                .conflicts_with("no_color")
+                                .help("Shift every other row by half a cell in unicode mode for a brick-like look."))
+                            .arg(Arg::with_name("crlf")
+                                .long("crlf")
+                                .help("End the lines of the text output with CRLF instead of LF."))
+                            .arg(Arg::with_name("stats")
+                                .long("stats")
+                                .help("Print the time taken by the simulation and its throughput to stderr."))
+                            .arg(Arg::with_name("canonical")
+                                .long("canonical")
+                                .help("Use the smallest rule equivalent under mirroring and complementing."))
                             .arg(Arg::with_name("print_rules")
                                 .short("p")
                                 .long("print-rules")
                                 .help("Print the rules"))
                             .get_matches();
 
-    // Safe to unwrap since arg is required and validated.
-    let rule = matches.value_of("rule").unwrap();
-    let rule = if rule.starts_with("0b") {
-        u8::from_str_radix(&rule[2..], 2).unwrap()
+    let rules = if matches.is_present("from_stdin") {
+        let mut list = String::new();
+        if let Err(e) = std::io::stdin().read_to_string(&mut list) {
+            eprintln!("error: couldn't read rules from stdin: {}", e);
+            std::process::exit(1);
+        }
+        let mut rules = Vec::new();
+        for (entry, rule) in parse_rule_list(&list) {
+            match rule {
+                Ok(rule) => rules.push(rule),
+                Err(e) => eprintln!("warning: skipping rule '{}': {}", entry, e),
+            }
+        }
+        if rules.is_empty() {
+            eprintln!("error: no valid rules read from stdin");
+            std::process::exit(1);
+        }
+        rules
     } else {
-        rule.parse().unwrap()
+        // Safe to unwrap since arg is required without --from-stdin and
+        // validated.
+        vec![parse_rule(matches.value_of("rule").unwrap()).unwrap()]
     };
-
-    let (term_width, term_height) = if let Some((Width(w), Height(h))) = terminal_size() {
-        (w, h)
+    let rules: Vec<u8> = if matches.is_present("canonical") {
+        rules
+            .into_iter()
+            .map(|rule| {
+                let canonical = canonical_rule(rule);
+                eprintln!(
+                    "Using rule {} as representative of rule {}",
+                    canonical, rule
+                );
+                canonical
+            })
+            .collect()
     } else {
-        // If we can't find the terminal size let's just use something small.
-        (80, 40)
+        rules
     };
+    // The options using a single rule conflict with --from-stdin.
+    let rule = rules[0];
 
-    // To fill the terminal when no width or height is specified we need to
-    // compensate for the extra data in braille symbols (4x2) and HALF BLOCKS
-    // (2x1).
-    let width: usize = if let Some(w) = matches.value_of("width") {
-        // Value is validated by clap as usize.
-        w.parse().unwrap()
+    let renderer = if matches.is_present("braille") {
+        Renderer::Braille
+    } else if matches.is_present("unicode") {
+        Renderer::Unicode
     } else {
-        if matches.is_present("braille") {
-            (term_width * 2) as usize
-        } else {
-            term_width as usize
-        }
+        Renderer::Ascii
     };
-    let height: usize = if let Some(h) = matches.value_of("iterations") {
-        // Value is validated by clap as usize
-        h.parse().unwrap()
+    let (fit_width, fit_height) = fit_to_terminal(renderer, matches.is_present("print_rules"));
+    // Staggered rows start with an extra space.
+    let fit_width = if matches.is_present("stagger") {
+        fit_width.saturating_sub(1).max(1)
     } else {
-        // Printing the rules require two columns at the top.
-        let offset = if matches.is_present("print_rules") {
-            3
-        } else {
-            1
-        };
-
-        let mult = if matches.is_present("braille") {
-            4
-        } else if matches.is_present("unicode") {
-            2
-        } else {
-            1
-        };
-        ((term_height - offset) * mult) as usize
+        fit_width
+    };
+
+    let strict = matches.is_present("strict");
+    let pattern = match matches
+        .value_of("seed")
+        .map(|p| parse_pattern(p, strict))
+        .transpose()
+    {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Values are validated by clap as usize.
+    let width: usize = match &pattern {
+        Some(p) => p.len(),
+        None => matches
+            .value_of("width")
+            .map_or(fit_width, |w| w.parse().unwrap()),
     };
+    let height: usize = matches
+        .value_of("iterations")
+        .map_or(fit_height, |h| h.parse().unwrap());
 
     if matches.is_present("print_rules") {
-        print_rules(rule);
+        print!("{}", rule_table_string(rule));
     }
 
-    let seed = if let Some(r) = matches.value_of("random") {
-        let mut rng = rand::thread_rng();
+    let mut rng = match matches.value_of("rng_seed") {
+        // Value is validated by clap as u64.
+        Some(s) => StdRng::seed_from_u64(s.parse().unwrap()),
+        None => StdRng::from_entropy(),
+    };
+
+    if matches.is_present("summary") {
+        let density = matches
+            .value_of("random")
+            .map_or(0.5, |r| r.parse().unwrap());
+        print!("{}", Ca::summary(rule, width, height, density, &mut rng));
+        return;
+    }
+
+    let (seed, seed_desc) = if let Some(p) = pattern {
+        let desc = p.iter().map(|c| c.to_string()).collect::<String>();
+        (p, format!("cells:{}", desc))
+    } else if let Some(r) = matches.value_of("random") {
         let density: f64 = r.parse().unwrap();
 
-        let mut res = Vec::with_capacity(width);
-        for _ in 0..width {
-            res.push(if rng.gen::<f64>() < density { 1 } else { 0 });
+        (
+            random_seed(width, density, &mut rng),
+            format!("random:{}", r),
+        )
+    } else if let Some(k) = matches.value_of("clusters") {
+        // Values are validated by clap.
+        let spread = matches
+            .value_of("spread")
+            .map_or(2., |s| s.parse().unwrap());
+        (
+            clustered_seed(width, k.parse().unwrap(), spread, &mut rng),
+            format!("clusters:{}:{}", k, spread),
+        )
+    } else if let Some(t) = matches.value_of("tile") {
+        match parse_pattern(t, strict).and_then(|p| tile_seed(&p, width)) {
+            Ok(seed) => (seed, format!("tile:{}", t)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if matches.is_present("centered") {
+        match centered_seed(width) {
+            Ok(seed) => (seed, "centered".to_string()),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
         }
-        res
     } else {
-        let mut res = vec![0; width];
-        let len = res.len();
-        res[len / 2] = 1;
-        res
+        // Value is validated by clap as usize.
+        let pos = matches
+            .value_of("seed_pos")
+            .map_or(width / 2, |p| p.parse().unwrap());
+        match single_cell_seed(width, pos) {
+            Ok(seed) => (seed, format!("single:{}", pos)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
     };
 
-    let mut ca = Ca::new(seed, rule);
+    // Value is validated by clap and has a default.
+    let boundary: BoundaryCondition = matches.value_of("boundary").unwrap().parse().unwrap();
 
-    let image = TermImage::new(ca.run(height));
-    if matches.is_present("braille") {
-        print!("{}", image.draw_braille(TermColor::White, TermColor::Black));
-    } else if matches.is_present("unicode") {
-        print!("{}", image.draw_unicode(TermColor::White, TermColor::Black));
+    let (line_ending, newline) = if matches.is_present("crlf") {
+        (LineEnding::CrLf, "\r\n")
     } else {
-        print!("{}", image.draw_ascii());
+        (LineEnding::Lf, "\n")
+    };
+
+    let align = match matches.value_of("align") {
+        Some("center") => VerticalAlign::Center,
+        Some("bottom") => VerticalAlign::Bottom,
+        _ => VerticalAlign::Top,
+    };
+    // Value is validated by clap as usize.
+    let sim_steps: usize = matches
+        .value_of("sim_steps")
+        .map_or(height, |s| s.parse().unwrap());
+    if sim_steps < height {
+        eprintln!(
+            "error: --iter ({}) can't be larger than --sim-steps ({})",
+            height, sim_steps
+        );
+        std::process::exit(1);
+    }
+    // Runs `rule` from the seed, returning the image, the header describing
+    // it and the simulation stats.
+    let simulate = |rule: u8| {
+        let mut ca = Ca::with_boundary(seed.clone(), rule, boundary);
+        let start = Instant::now();
+        let gens = ca.run_sampled_evenly(sim_steps, height);
+        let stats = format_stats(start.elapsed(), width * sim_steps);
+        let mut image = TermImage::new(gens).align(height, align);
+        if matches.is_present("invert") {
+            image.invert();
+        }
+        let header = format!(
+            "rule={} seed={} width={} iter={} sim={} boundary={}",
+            rule, seed_desc, width, height, sim_steps, boundary
+        );
+        if matches.is_present("header") {
+            image.set_comment(&header);
+        }
+        image.set_line_ending(line_ending);
+        (image, header, stats)
+    };
+
+    if let Some(dir) = matches.value_of("output_dir") {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("error: couldn't create {}: {}", dir, e);
+            std::process::exit(1);
+        }
+        // Value is validated by clap and has a default.
+        let format = matches.value_of("output_format").unwrap();
+        for rule in rules {
+            let (image, header, stats) = simulate(rule);
+            let header = Some(header.as_str()).filter(|_| matches.is_present("header"));
+            let path = Path::new(dir).join(format!("rule{}.{}", rule, format));
+            if let Err(e) = std::fs::write(&path, encode_image(&path, &image, header, newline)) {
+                eprintln!("error: couldn't write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+            if matches.is_present("stats") {
+                eprintln!("rule {}: {}", rule, stats);
+            }
+        }
+        return;
+    }
+
+    if matches.is_present("jsonl") {
+        let mut ca = Ca::with_boundary(seed, rule, boundary);
+        let stdout = std::io::stdout();
+        if let Err(e) = ca.stream_jsonl(height, &mut stdout.lock()) {
+            eprintln!("error: couldn't write generations: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let (image, header, stats) = simulate(rule);
+
+    if let Some(path) = matches.value_of("output") {
+        let header = Some(header.as_str()).filter(|_| matches.is_present("header"));
+        if let Err(e) = std::fs::write(path, encode_image(Path::new(path), &image, header, newline))
+        {
+            eprintln!("error: couldn't write {}: {}", path, e);
+            std::process::exit(1);
+        }
+        if matches.is_present("stats") {
+            eprintln!("{}", stats);
+        }
+        return;
+    }
+
+    if matches.is_present("header") {
+        print!("# {}{}", header, newline);
+    }
+    // Values are validated by clap as colors and have defaults.
+    let fg: TermColor = matches.value_of("fg").unwrap().parse().unwrap();
+    let bg: TermColor = matches.value_of("bg").unwrap().parse().unwrap();
+    let colors = Some((fg, bg)).filter(|_| !matches.is_present("no_color"));
+    print!(
+        "{}",
+        render(&image, renderer, colors, matches.is_present("stagger"))
+    );
+    if matches.is_present("stats") {
+        eprintln!("{}", stats);
     }
 }
 
-fn print_rules(rule: u8) {
-    let top: String = (0..8).map(|i| format!(" {:03b} |", i)).collect();
-    let bottom: String = format!("{:08b}", rule)
-        .bytes()
-        .map(|i| format!("  {}  |", i as char))
-        .collect();
-    println!("|{}", top);
-    println!("|{}", bottom);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule_number_and_binary() {
+        assert_eq!(Ok(110), parse_rule("110"));
+        assert_eq!(Ok(110), parse_rule("0b01101110"));
+        assert!(parse_rule("256").is_err());
+    }
+
+    #[test]
+    fn test_parse_rule_hex() {
+        assert_eq!(Ok(110), parse_rule("0x6e"));
+        assert_eq!(Ok(110), parse_rule("0x6E"));
+        assert_eq!(parse_rule("110"), parse_rule("0x6e"));
+        assert_eq!(parse_rule("0b01101110"), parse_rule("0x6e"));
+        assert_eq!(Ok(255), parse_rule("0xff"));
+        assert!(parse_rule("0x100").is_err());
+        assert!(parse_rule("0x").is_err());
+        assert!(parse_rule("0xg1").is_err());
+    }
+
+    #[test]
+    fn test_parse_rule_list() {
+        let rules = parse_rule_list("30,90, 110\n0x6e\n\nRule 45,300,\n");
+        let entries: Vec<&str> = rules.iter().map(|(entry, _)| *entry).collect();
+        assert_eq!(vec!["30", "90", "110", "0x6e", "Rule 45", "300"], entries);
+        let parsed: Vec<Option<u8>> = rules.into_iter().map(|(_, rule)| rule.ok()).collect();
+        assert_eq!(
+            vec![Some(30), Some(90), Some(110), Some(110), Some(45), None],
+            parsed
+        );
+        assert!(parse_rule_list(" \n,").is_empty());
+    }
+
+    #[test]
+    fn test_render_no_color() {
+        let image = TermImage::new(Ca::new(vec![0, 0, 0, 1, 0, 0, 0, 0], 90).run(8));
+        for renderer in [Renderer::Ascii, Renderer::Unicode, Renderer::Braille] {
+            let plain = render(&image, renderer, None, false);
+            assert!(!plain.is_empty());
+            assert!(!plain.bytes().any(|b| b == 0x1b));
+        }
+        let colored = render(
+            &image,
+            Renderer::Unicode,
+            Some((TermColor::White, TermColor::Black)),
+            false,
+        );
+        assert!(colored.bytes().any(|b| b == 0x1b));
+    }
+
+    #[test]
+    fn test_format_stats() {
+        assert_eq!(
+            "simulated 1000 cells in 500.000 ms (2000 cells/s)",
+            format_stats(Duration::from_millis(500), 1000)
+        );
+    }
+
+    #[test]
+    fn test_parse_rule_alias() {
+        assert_eq!(Ok(90), parse_rule("sierpinski"));
+        assert_eq!(Ok(30), parse_rule("rule30"));
+        assert_eq!(Ok(184), parse_rule("traffic"));
+        assert_eq!(Ok(110), parse_rule("Rule 110"));
+    }
+
+    #[test]
+    fn test_parse_rule_unknown_alias() {
+        let err = parse_rule("glider").unwrap_err();
+        assert!(err.contains("sierpinski"));
+    }
 }
@@ -0,0 +1,597 @@
+//! Helpers for building seeds (initial states) for the automaton.
+
+#[cfg(feature = "image")]
+use std::fs;
+#[cfg(feature = "image")]
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::CaError;
+
+/// Generates a random seed where each cell is ON with probability `density`.
+///
+/// # Arguments
+/// * `width` - The width of the resulting seed.
+/// * `density` - The probability, between 0 and 1, of a cell being ON.
+/// * `rng` - The random number generator to draw from.
+pub fn random_seed<R: Rng>(width: usize, density: f64, rng: &mut R) -> Vec<u8> {
+    (0..width)
+        .map(|_| if rng.gen::<f64>() < density { 1 } else { 0 })
+        .collect()
+}
+
+/// Generates a random seed where cell `i` is ON with probability `probs[i]`.
+///
+/// This allows gradients or other structured initial conditions, the seed is
+/// as wide as `probs`.
+///
+/// # Arguments
+/// * `probs` - The probability, between 0 and 1, of each cell being ON.
+/// * `rng` - The random number generator to draw from.
+///
+/// # Errors
+/// Returns an error if any probability is outside of `[0, 1]`.
+pub fn random_seed_weighted<R: Rng>(probs: &[f64], rng: &mut R) -> Result<Vec<u8>, CaError> {
+    if let Some(p) = probs.iter().find(|p| !(0. ..=1.).contains(*p)) {
+        return Err(CaError::InvalidProbability(*p));
+    }
+    Ok(probs
+        .iter()
+        .map(|&p| if rng.gen::<f64>() < p { 1 } else { 0 })
+        .collect())
+}
+
+/// Generates a random seed with the ON cells gathered in clusters.
+///
+/// `clusters` centers are picked uniformly at random and each cell is ON with
+/// probability `exp(-d^2 / (2 * spread^2))`, where `d` is the distance (around
+/// the ring) to the nearest center. This gives blobs of roughly `2 * spread`
+/// cells around each center.
+///
+/// # Arguments
+/// * `width` - The width of the resulting seed.
+/// * `clusters` - The number of cluster centers.
+/// * `spread` - The standard deviation, in cells, of each cluster.
+/// * `rng` - The random number generator to draw from.
+pub fn clustered_seed<R: Rng>(width: usize, clusters: usize, spread: f64, rng: &mut R) -> Vec<u8> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let centers: Vec<usize> = (0..clusters).map(|_| rng.gen_range(0, width)).collect();
+    (0..width)
+        .map(|i| {
+            let distance = centers
+                .iter()
+                .map(|&c| {
+                    let d = i.abs_diff(c);
+                    d.min(width - d)
+                })
+                .min();
+            let p = match distance {
+                Some(d) if spread > 0. => (-((d * d) as f64) / (2. * spread * spread)).exp(),
+                Some(0) => 1.,
+                _ => 0.,
+            };
+            if rng.gen::<f64>() < p {
+                1
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+/// Returns a seed of length `width` with a single ON cell at `pos`.
+///
+/// # Arguments
+/// * `width` - The width of the resulting seed.
+/// * `pos` - The index of the ON cell.
+///
+/// # Errors
+/// Returns an error if `pos` is outside of the seed.
+pub fn single_cell_seed(width: usize, pos: usize) -> Result<Vec<u8>, CaError> {
+    if pos >= width {
+        return Err(CaError::OutOfBounds { index: pos, width });
+    }
+    let mut seed = vec![0; width];
+    seed[pos] = 1;
+    Ok(seed)
+}
+
+/// Returns a seed of length `width` that is mirror symmetric around its
+/// center.
+///
+/// An odd width gets a single ON cell in the middle, while an even width,
+/// which has no middle cell, gets the two cells on either side of the center.
+/// Unlike `single_cell_seed(width, width / 2)` this preserves the symmetry of
+/// mirror-symmetric rules.
+///
+/// # Arguments
+/// * `width` - The width of the resulting seed.
+///
+/// # Errors
+/// Returns an error if `width` is 0.
+pub fn centered_seed(width: usize) -> Result<Vec<u8>, CaError> {
+    let mut seed = single_cell_seed(width, width / 2)?;
+    if width.is_multiple_of(2) {
+        seed[width / 2 - 1] = 1;
+    }
+    Ok(seed)
+}
+
+/// Parses the cells of a seed from a hand-written pattern of 0's and 1's.
+///
+/// Whitespace is ignored and `#` starts a comment running to the end of the
+/// line, so a pattern like `"0110 0110  # glider"` is accepted. Use
+/// [`from_pattern_strict`] to reject anything but 0's and 1's.
+///
+/// # Errors
+/// Returns an error if the pattern contains any other character outside of a
+/// comment.
+pub fn from_pattern(s: &str) -> Result<Vec<u8>, CaError> {
+    let cells: String = s
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(str::chars)
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    from_pattern_strict(&cells)
+}
+
+/// Parses the cells of a seed from a string of only 0's and 1's.
+///
+/// # Errors
+/// Returns an error if the pattern contains any other character.
+pub fn from_pattern_strict(s: &str) -> Result<Vec<u8>, CaError> {
+    s.chars()
+        .map(|c| match c {
+            '0' => Ok(0),
+            '1' => Ok(1),
+            _ => Err(CaError::InvalidPattern(c)),
+        })
+        .collect()
+}
+
+/// Repeats `pattern` until it fills a seed of length `width`.
+///
+/// The last repetition is cut short if `width` isn't a multiple of the
+/// pattern length.
+///
+/// # Arguments
+/// * `pattern` - The motif to repeat.
+/// * `width` - The width of the resulting seed.
+///
+/// # Errors
+/// Returns an error if `pattern` is empty or longer than `width`.
+pub fn tile_seed(pattern: &[u8], width: usize) -> Result<Vec<u8>, CaError> {
+    if pattern.is_empty() {
+        return Err(CaError::EmptyData("pattern"));
+    }
+    if pattern.len() > width {
+        return Err(CaError::SeedTooShort {
+            width,
+            required: pattern.len(),
+        });
+    }
+    Ok(pattern.iter().cloned().cycle().take(width).collect())
+}
+
+/// Builds a seed from a repeating `background` with individual cells
+/// overwritten.
+///
+/// This is useful for setting up collisions between particles travelling on a
+/// periodic background, like the ether of rule 110.
+///
+/// # Arguments
+/// * `width` - The width of the resulting seed.
+/// * `background` - The motif tiled across the seed, see [`tile_seed`].
+/// * `injections` - Pairs of `(index, value)` written on top of the
+///   background.
+///
+/// # Errors
+/// Returns an error if the background can't be tiled or if any injection
+/// index is outside of the seed.
+pub fn seed_with(
+    width: usize,
+    background: &[u8],
+    injections: &[(usize, u8)],
+) -> Result<Vec<u8>, CaError> {
+    let mut seed = tile_seed(background, width)?;
+    for &(index, value) in injections {
+        if index >= width {
+            return Err(CaError::OutOfBounds { index, width });
+        }
+        seed[index] = value;
+    }
+    Ok(seed)
+}
+
+/// Reads a seed from the first row of a PNG or Netpbm image file, where dark
+/// pixels become ON cells.
+///
+/// See [`seed_from_image_buffer`] for how the pixels are converted. Only
+/// available with the `image` feature, see [`seed_from_pnm`] for reading
+/// Netpbm images without it.
+///
+/// # Arguments
+/// * `path` - The image file to read.
+/// * `threshold` - Pixels with a gray level, from 0 to 255, below this value
+///   are ON.
+///
+/// # Errors
+/// Returns an error if the file can't be read or isn't a supported image.
+#[cfg(feature = "image")]
+pub fn seed_from_image<P: AsRef<Path>>(path: P, threshold: u8) -> Result<Vec<u8>, CaError> {
+    let path = path.as_ref();
+    let data = fs::read(path)
+        .map_err(|e| CaError::Io(format!("couldn't read {}: {}", path.display(), e)))?;
+    let image = image::load_from_memory(&data)
+        .map_err(|e| CaError::InvalidImage(format!("couldn't decode {}: {}", path.display(), e)))?;
+    seed_from_image_buffer(&image, threshold)
+}
+
+/// Reads a seed from the first row of a decoded image, where dark pixels
+/// become ON cells.
+///
+/// The pixels are converted to gray by the `image` crate and composited onto
+/// white using their alpha channel, so transparent pixels are OFF and half
+/// transparent ones are lighter than their color. Only available with the
+/// `image` feature.
+///
+/// # Arguments
+/// * `image` - The image to read.
+/// * `threshold` - Pixels with a gray level, from 0 to 255, below this value
+///   are ON.
+///
+/// # Errors
+/// Returns an error if the image has no rows.
+#[cfg(feature = "image")]
+pub fn seed_from_image_buffer(
+    image: &image::DynamicImage,
+    threshold: u8,
+) -> Result<Vec<u8>, CaError> {
+    if image.height() == 0 {
+        return Err(CaError::InvalidImage(String::from("image has no rows")));
+    }
+    let gray = image.to_luma_alpha8();
+    Ok((0..gray.width())
+        .map(|x| {
+            let [level, alpha] = gray.get_pixel(x, 0).0;
+            let (level, alpha) = (u32::from(level), u32::from(alpha));
+            let level = (level * alpha + 255 * (255 - alpha)) / 255;
+            if level < u32::from(threshold) {
+                1
+            } else {
+                0
+            }
+        })
+        .collect())
+}
+
+/// Reads a seed from the first row of a Netpbm image, where dark pixels become
+/// ON cells.
+///
+/// Graymaps (`P2` and `P5`) and pixmaps (`P3` and `P6`) are supported, which
+/// most image editors can export. Color pixels are converted to gray using
+/// the Rec. 601 luma weights and every sample is scaled to 0-255 before being
+/// compared with `threshold`. These formats have no alpha channel, see
+/// [`seed_from_image`] with the `image` feature for images with one.
+///
+/// Unlike [`seed_from_image`] this doesn't need any dependencies.
+///
+/// # Arguments
+/// * `data` - The contents of the image file.
+/// * `threshold` - Pixels with a gray level, from 0 to 255, below this value
+///   are ON.
+///
+/// # Errors
+/// Returns an error if the image is malformed or in an unsupported format.
+pub fn seed_from_pnm(data: &[u8], threshold: u8) -> Result<Vec<u8>, CaError> {
+    // The header is whitespace separated tokens, with comments from # to the
+    // end of the line, followed by a single whitespace character.
+    let mut pos = 0;
+    let mut header = Vec::new();
+    while header.len() < 4 {
+        while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+            if data[pos] == b'#' {
+                while pos < data.len() && data[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        let start = pos;
+        while pos < data.len() && !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(CaError::InvalidImage(String::from(
+                "image header is truncated",
+            )));
+        }
+        header.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+    }
+    let (ascii, channels) = match header[0].as_str() {
+        "P2" => (true, 1),
+        "P3" => (true, 3),
+        "P5" => (false, 1),
+        "P6" => (false, 3),
+        magic => {
+            return Err(CaError::InvalidImage(format!(
+                "unsupported image format '{}'",
+                magic
+            )))
+        }
+    };
+    let parse = |token: &str, name: &str| {
+        token
+            .parse::<usize>()
+            .map_err(|_| CaError::InvalidImage(format!("invalid image {} '{}'", name, token)))
+    };
+    let width = parse(&header[1], "width")?;
+    let maxval = parse(&header[3], "maxval")?;
+    if maxval == 0 || maxval > 65535 {
+        return Err(CaError::InvalidImage(format!(
+            "invalid image maxval '{}'",
+            maxval
+        )));
+    }
+
+    let count = width
+        .checked_mul(channels)
+        .ok_or_else(|| CaError::InvalidImage(format!("image width {} is too large", width)))?;
+    let samples: Vec<usize> = if ascii {
+        String::from_utf8_lossy(&data[pos..])
+            .split_ascii_whitespace()
+            .take(count)
+            .map(|token| parse(token, "sample"))
+            .collect::<Result<_, _>>()?
+    } else {
+        let bytes = if maxval > 255 { 2 } else { 1 };
+        let raster = data.get(pos + 1..).unwrap_or(&[]);
+        raster
+            .chunks(bytes)
+            .take(count)
+            .map(|b| b.iter().fold(0, |acc, &x| acc << 8 | x as usize))
+            .collect()
+    };
+    if samples.len() < count {
+        return Err(CaError::InvalidImage(String::from(
+            "image data is truncated",
+        )));
+    }
+
+    Ok(samples
+        .chunks(channels)
+        .map(|pixel| {
+            let gray = match pixel {
+                [r, g, b] => (299 * r + 587 * g + 114 * b) / 1000,
+                _ => pixel[0],
+            };
+            if gray * 255 / maxval < threshold as usize {
+                1
+            } else {
+                0
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_random_seed_weighted() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(Ok(vec![0, 1]), random_seed_weighted(&[0.0, 1.0], &mut rng));
+        assert_eq!(Ok(vec![]), random_seed_weighted(&[], &mut rng));
+    }
+
+    #[test]
+    fn test_random_seed_weighted_invalid() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!(random_seed_weighted(&[0.5, 1.5], &mut rng).is_err());
+        assert!(random_seed_weighted(&[-0.1], &mut rng).is_err());
+        assert!(random_seed_weighted(&[f64::NAN], &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_clustered_seed_reproducible() {
+        let a = clustered_seed(50, 3, 2., &mut StdRng::seed_from_u64(7));
+        let b = clustered_seed(50, 3, 2., &mut StdRng::seed_from_u64(7));
+        assert_eq!(a, b);
+        assert_eq!(50, a.len());
+    }
+
+    #[test]
+    fn test_clustered_seed_zero_spread() {
+        // Without any spread only the centers themselves are ON.
+        let seed = clustered_seed(50, 1, 0., &mut StdRng::seed_from_u64(3));
+        assert_eq!(1, seed.iter().filter(|&&c| c == 1).count());
+    }
+
+    #[test]
+    fn test_clustered_seed_no_clusters() {
+        let seed = clustered_seed(20, 0, 2., &mut StdRng::seed_from_u64(3));
+        assert_eq!(vec![0; 20], seed);
+    }
+
+    #[test]
+    fn test_single_cell_seed() {
+        assert_eq!(Ok(vec![1, 0, 0, 0]), single_cell_seed(4, 0));
+        assert_eq!(Ok(vec![0, 0, 0, 1]), single_cell_seed(4, 3));
+        assert!(single_cell_seed(4, 4).is_err());
+        assert!(single_cell_seed(0, 0).is_err());
+    }
+
+    #[test]
+    fn test_centered_seed() {
+        assert_eq!(Ok(vec![0, 0, 1, 0, 0]), centered_seed(5));
+        assert_eq!(Ok(vec![0, 0, 1, 1, 0, 0]), centered_seed(6));
+        assert_eq!(Ok(vec![1]), centered_seed(1));
+        assert_eq!(Ok(vec![1, 1]), centered_seed(2));
+        assert!(centered_seed(0).is_err());
+        for width in 1..10 {
+            let seed = centered_seed(width).unwrap();
+            let mut mirrored = seed.clone();
+            mirrored.reverse();
+            assert_eq!(seed, mirrored);
+        }
+    }
+
+    #[test]
+    fn test_from_pattern_whitespace() {
+        assert_eq!(Ok(vec![0, 1, 1, 0, 1]), from_pattern(" 01 10\t1 "));
+        assert_eq!(Ok(vec![0, 1, 1, 0]), from_pattern("01\r\n10\n"));
+    }
+
+    #[test]
+    fn test_from_pattern_comments() {
+        assert_eq!(
+            Ok(vec![0, 1, 1, 0, 1]),
+            from_pattern("# a comment\n011 # the left part\n01 #1111")
+        );
+        assert_eq!(Ok(vec![]), from_pattern("# only a comment"));
+    }
+
+    #[test]
+    fn test_from_pattern_invalid() {
+        assert!(from_pattern("01x0").is_err());
+        assert!(from_pattern("0120 # 3").is_err());
+    }
+
+    #[test]
+    fn test_from_pattern_strict() {
+        assert_eq!(Ok(vec![0, 1, 1, 0]), from_pattern_strict("0110"));
+        assert!(from_pattern_strict("01 10").is_err());
+        assert!(from_pattern_strict("0110 # comment").is_err());
+    }
+
+    #[test]
+    fn test_seed_from_pnm_graymap() {
+        let ascii = b"P2\n# a comment\n4 2\n255\n0 255 100 200\n255 255 255 255\n";
+        assert_eq!(Ok(vec![1, 0, 1, 0]), seed_from_pnm(ascii, 128));
+
+        let mut binary = b"P5 4 1 15\n".to_vec();
+        binary.extend_from_slice(&[0, 15, 7, 8]);
+        assert_eq!(Ok(vec![1, 0, 1, 0]), seed_from_pnm(&binary, 128));
+    }
+
+    #[test]
+    fn test_seed_from_pnm_pixmap() {
+        let mut binary = b"P6\n3 1\n255\n".to_vec();
+        binary.extend_from_slice(&[0, 0, 0, 255, 255, 255, 0, 0, 255]);
+        assert_eq!(Ok(vec![1, 0, 1]), seed_from_pnm(&binary, 128));
+        let ascii = b"P3 2 1 255  255 255 0  255 0 0";
+        assert_eq!(Ok(vec![0, 1]), seed_from_pnm(ascii, 128));
+    }
+
+    #[test]
+    fn test_seed_from_pnm_threshold() {
+        let image = b"P2 3 1 255 0 127 128";
+        assert_eq!(Ok(vec![0, 0, 0]), seed_from_pnm(image, 0));
+        assert_eq!(Ok(vec![1, 1, 0]), seed_from_pnm(image, 128));
+        assert_eq!(Ok(vec![1, 1, 1]), seed_from_pnm(image, 255));
+    }
+
+    #[test]
+    fn test_seed_from_pnm_invalid() {
+        assert!(seed_from_pnm(b"P1 2 1 0 1", 128).is_err());
+        assert!(seed_from_pnm(b"P2 2 1", 128).is_err());
+        assert!(seed_from_pnm(b"P2 2 1 255 0", 128).is_err());
+        assert!(seed_from_pnm(b"P5 2 1 255\n\x00", 128).is_err());
+        assert!(seed_from_pnm(b"P2 2 1 0 0 0", 128).is_err());
+    }
+
+    #[test]
+    fn test_seed_from_pnm_huge_width() {
+        let header = format!("P6 {} 1 255\n", usize::MAX / 2);
+        assert!(matches!(
+            seed_from_pnm(header.as_bytes(), 128),
+            Err(CaError::InvalidImage(_))
+        ));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_seed_from_image_buffer() {
+        use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+
+        let mut gray = GrayImage::new(4, 2);
+        for (x, level) in [0, 200, 100, 255].iter().enumerate() {
+            gray.put_pixel(x as u32, 0, Luma([*level]));
+        }
+        let gray = DynamicImage::ImageLuma8(gray);
+        assert_eq!(Ok(vec![1, 0, 1, 0]), seed_from_image_buffer(&gray, 128));
+        assert_eq!(Ok(vec![1, 1, 1, 0]), seed_from_image_buffer(&gray, 255));
+
+        // Transparent pixels are OFF whatever their color, half transparent
+        // ones are lighter.
+        let mut rgba = RgbaImage::new(3, 1);
+        rgba.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+        rgba.put_pixel(1, 0, Rgba([0, 0, 0, 0]));
+        rgba.put_pixel(2, 0, Rgba([0, 0, 0, 100]));
+        let rgba = DynamicImage::ImageRgba8(rgba);
+        assert_eq!(Ok(vec![1, 0, 0]), seed_from_image_buffer(&rgba, 128));
+        assert_eq!(Ok(vec![1, 0, 1]), seed_from_image_buffer(&rgba, 200));
+
+        assert!(seed_from_image_buffer(&DynamicImage::new_luma8(2, 0), 128).is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_seed_from_image_file() {
+        use image::{DynamicImage, ImageFormat, Luma};
+
+        let path = std::env::temp_dir().join(format!("eca1d-seed-{}.png", std::process::id()));
+        let mut image = image::GrayImage::new(3, 1);
+        image.put_pixel(1, 0, Luma([255]));
+        DynamicImage::ImageLuma8(image)
+            .save_with_format(&path, ImageFormat::Png)
+            .unwrap();
+        let seed = seed_from_image(&path, 128);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Ok(vec![1, 0, 1]), seed);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_seed_from_image_missing_file() {
+        assert!(seed_from_image("/nonexistent/seed.pgm", 128).is_err());
+    }
+
+    #[test]
+    fn test_tile_seed_exact() {
+        assert_eq!(Ok(vec![0, 1, 1, 0, 1, 1]), tile_seed(&[0, 1, 1], 6));
+    }
+
+    #[test]
+    fn test_tile_seed_partial() {
+        assert_eq!(Ok(vec![0, 1, 1, 0, 1]), tile_seed(&[0, 1, 1], 5));
+    }
+
+    #[test]
+    fn test_tile_seed_over_length() {
+        assert!(tile_seed(&[0, 1, 1, 0], 3).is_err());
+        assert!(tile_seed(&[], 3).is_err());
+    }
+
+    #[test]
+    fn test_seed_with_injections() {
+        assert_eq!(
+            Ok(vec![1, 0, 0, 0, 1, 1]),
+            seed_with(6, &[0, 0, 1], &[(0, 1), (2, 0), (4, 1)])
+        );
+    }
+
+    #[test]
+    fn test_seed_with_out_of_bounds_injection() {
+        assert!(seed_with(6, &[0, 0, 1], &[(6, 1)]).is_err());
+    }
+}
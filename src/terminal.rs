@@ -0,0 +1,92 @@
+//! Fitting images to the size of the terminal.
+
+#[cfg(feature = "terminal")]
+use terminal_size::{terminal_size, Height, Width};
+
+use crate::Renderer;
+
+/// Returns the `(width, rows)` of a simulation filling a terminal of the given
+/// size when drawn with `renderer`.
+///
+/// Braille symbols hold 4x2 cells and HALF BLOCKS 2x1 cells, which is
+/// compensated for so that the image fills the terminal. One line is left for
+/// the prompt, and two more for the rule table if `print_rules` is set. At
+/// least one row is always simulated, even if the terminal is too small to
+/// show it.
+///
+/// # Arguments
+/// * `term_width` - The width of the terminal in characters.
+/// * `term_height` - The height of the terminal in lines.
+/// * `renderer` - The renderer the image will be drawn with.
+/// * `print_rules` - Whether the rule table is printed above the image.
+pub fn fit_to_size(
+    term_width: u16,
+    term_height: u16,
+    renderer: Renderer,
+    print_rules: bool,
+) -> (usize, usize) {
+    let width = match renderer {
+        Renderer::Braille => term_width as usize * 2,
+        Renderer::Ascii | Renderer::Unicode => term_width as usize,
+    };
+    // Printing the rules require two lines at the top.
+    let offset = if print_rules { 3 } else { 1 };
+    let mult = match renderer {
+        Renderer::Braille => 4,
+        Renderer::Unicode => 2,
+        Renderer::Ascii => 1,
+    };
+    let lines = term_height.saturating_sub(offset).max(1);
+    (width, lines as usize * mult)
+}
+
+/// Returns the `(width, rows)` of a simulation filling the current terminal
+/// when drawn with `renderer`, see [`fit_to_size`].
+///
+/// If the size of the terminal can't be found a small 80x40 terminal is
+/// assumed.
+///
+/// # Arguments
+/// * `renderer` - The renderer the image will be drawn with.
+/// * `print_rules` - Whether the rule table is printed above the image.
+#[cfg(feature = "terminal")]
+pub fn fit_to_terminal(renderer: Renderer, print_rules: bool) -> (usize, usize) {
+    let (term_width, term_height) = if let Some((Width(w), Height(h))) = terminal_size() {
+        (w, h)
+    } else {
+        (80, 40)
+    };
+    fit_to_size(term_width, term_height, renderer, print_rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_to_size_ascii() {
+        assert_eq!((80, 39), fit_to_size(80, 40, Renderer::Ascii, false));
+        assert_eq!((80, 37), fit_to_size(80, 40, Renderer::Ascii, true));
+    }
+
+    #[test]
+    fn test_fit_to_size_unicode() {
+        assert_eq!((80, 78), fit_to_size(80, 40, Renderer::Unicode, false));
+        assert_eq!((80, 74), fit_to_size(80, 40, Renderer::Unicode, true));
+    }
+
+    #[test]
+    fn test_fit_to_size_braille() {
+        assert_eq!((160, 156), fit_to_size(80, 40, Renderer::Braille, false));
+        assert_eq!((160, 148), fit_to_size(80, 40, Renderer::Braille, true));
+    }
+
+    #[test]
+    fn test_fit_to_size_tiny_terminal() {
+        assert_eq!((80, 1), fit_to_size(80, 1, Renderer::Ascii, true));
+        assert_eq!((80, 1), fit_to_size(80, 0, Renderer::Ascii, false));
+        assert_eq!((80, 1), fit_to_size(80, 3, Renderer::Ascii, true));
+        assert_eq!((80, 2), fit_to_size(80, 1, Renderer::Unicode, true));
+        assert_eq!((160, 4), fit_to_size(80, 2, Renderer::Braille, true));
+    }
+}